@@ -15,7 +15,21 @@ cargo check --features std,async
 cargo check --features tokio
 cargo check --features futures
 cargo check --features tokio,futures
+cargo check --features embedded-hal-async
+cargo check --features embedded-hal-nb
+cargo check --features embedded-hal-02
+cargo check --features embedded-storage
+cargo check --features embedded-storage-async
+cargo check --features heapless
+cargo check --features heapless,async
+cargo check --features bytes
+cargo check --features bytes,std,async
+cargo check --features ufmt
+cargo check --features ufmt,async
+cargo check --features log,std,async
+cargo check --features defmt,std,async
+cargo test --features std,async,log,tokio,futures,embedded-hal-async,embedded-hal-nb,embedded-hal-02,embedded-storage-async,heapless,bytes,ufmt
 RUSTUP_TOOLCHAIN=stable cargo check
 RUSTUP_TOOLCHAIN=stable cargo check --features alloc
 RUSTUP_TOOLCHAIN=stable cargo check --features std
-cargo rustdoc --features std,async,defmt,tokio,futures
\ No newline at end of file
+cargo rustdoc --features std,async,defmt,tokio,futures,embedded-hal-async,embedded-hal-nb,embedded-hal-02,embedded-storage-async,heapless,bytes,ufmt
\ No newline at end of file
@@ -0,0 +1,187 @@
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+/// Strategy for waiting while a future driven by [`BlockOn`] is pending.
+///
+/// The future is polled with a waker that does nothing, so this is called between polls
+/// regardless of whether it was woken. Closures implement this trait, for hooking in e.g. a
+/// wait-for-event instruction.
+pub trait Wait {
+    /// Wait before polling again.
+    fn wait(&mut self);
+}
+
+impl<F: FnMut()> Wait for F {
+    fn wait(&mut self) {
+        self()
+    }
+}
+
+/// Busy-wait, with [`spin_loop`](core::hint::spin_loop) hints.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Spin;
+
+impl Wait for Spin {
+    fn wait(&mut self) {
+        core::hint::spin_loop()
+    }
+}
+
+pub(super) fn noop_waker() -> Waker {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| RAW, |_| {}, |_| {}, |_| {});
+    const RAW: RawWaker = RawWaker::new(core::ptr::null(), &VTABLE);
+    // Safety: the vtable functions do nothing, so the contract is trivially upheld.
+    unsafe { Waker::from_raw(RAW) }
+}
+
+/// Run a future to completion, calling `wait` whenever it's pending.
+pub fn block_on<F: Future>(fut: F, wait: &mut impl Wait) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(res) = fut.as_mut().poll(&mut cx) {
+            return res;
+        }
+        wait.wait();
+    }
+}
+
+/// Adapter from [`asynch`](crate::asynch) traits to [`blocking`](crate::blocking) traits.
+///
+/// Each operation is driven to completion by a tiny built-in executor, see [`block_on`]. This
+/// is only suitable for drivers that make progress on their own, such as by interrupts or
+/// DMA, or by being polled, since wakeups are ignored.
+pub struct BlockOn<T, W = Spin> {
+    inner: T,
+    wait: W,
+}
+
+impl<T> BlockOn<T> {
+    /// Create a new adapter, busy-waiting between polls.
+    pub fn new(inner: T) -> Self {
+        Self { inner, wait: Spin }
+    }
+}
+
+impl<T, W: Wait> BlockOn<T, W> {
+    /// Create a new adapter, with the given wait strategy.
+    pub fn with_wait(inner: T, wait: W) -> Self {
+        Self { inner, wait }
+    }
+
+    /// Consume the adapter, returning the inner object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: crate::Io, W> crate::Io for BlockOn<T, W> {
+    type Error = T::Error;
+}
+
+impl<T: crate::asynch::Read, W: Wait> crate::blocking::Read for BlockOn<T, W> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        block_on(self.inner.read(buf), &mut self.wait)
+    }
+}
+
+impl<T: crate::asynch::BufRead, W: Wait> crate::blocking::BufRead for BlockOn<T, W> {
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        block_on(self.inner.fill_buf(), &mut self.wait)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+impl<T: crate::asynch::Write, W: Wait> crate::blocking::Write for BlockOn<T, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        block_on(self.inner.write(buf), &mut self.wait)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        block_on(self.inner.flush(), &mut self.wait)
+    }
+}
+
+impl<T: crate::asynch::Seek, W: Wait> crate::blocking::Seek for BlockOn<T, W> {
+    fn seek(&mut self, pos: crate::SeekFrom) -> Result<u64, Self::Error> {
+        block_on(self.inner.seek(pos), &mut self.wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::{BufRead, Read, Write};
+    use crate::chaos::{Chaotic, ChaoticError, Fault};
+    use crate::ErrorKind;
+
+    #[test]
+    fn block_on_waits_while_pending() {
+        let mut waits = 0;
+        let fut = crate::asynch::yield_n(3);
+        block_on(fut, &mut || waits += 1);
+        assert_eq!(waits, 3);
+    }
+
+    #[test]
+    fn read_write() {
+        let faults = [Fault::limit(2).with_pending(2), Fault::NONE.with_pending(1)];
+        let mut waits = 0;
+        let mut r = BlockOn::with_wait(Chaotic::new(&b"abcdef"[..], faults.into_iter()), || {
+            waits += 1
+        });
+        let mut buf = [0; 8];
+        assert_eq!(r.read(&mut buf), Ok(2));
+        assert_eq!(r.fill_buf(), Ok(&b"cdef"[..]));
+        r.consume(4);
+        assert_eq!(r.read(&mut buf), Ok(0));
+        drop(r);
+        assert_eq!(waits, 3);
+
+        let faults = [
+            Fault::limit(3).with_pending(1),
+            Fault::error(ErrorKind::TimedOut),
+        ];
+        let mut w = BlockOn::new(Chaotic::new(&mut buf[..], faults.into_iter()));
+        assert_eq!(w.write(b"hello"), Ok(3));
+        assert_eq!(w.flush(), Err(ChaoticError::Injected(ErrorKind::TimedOut)));
+        assert_eq!(w.write(b"lo"), Ok(2));
+        assert_eq!(w.flush(), Ok(()));
+        assert_eq!(&buf[..5], b"hello");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn seek() {
+        use crate::blocking::Seek;
+        use crate::mock::{Mock, Transaction};
+        use crate::SeekFrom;
+
+        let mock = Mock::new([
+            Transaction::Seek(SeekFrom::End(-1), 9),
+            Transaction::Seek(SeekFrom::Start(0), 0),
+            Transaction::Seek(SeekFrom::Current(0), 0),
+        ]);
+        let faults = [Fault::NONE.with_pending(4)];
+        let mut s = BlockOn::new(Chaotic::new(mock, faults.into_iter()));
+        assert_eq!(s.seek(SeekFrom::End(-1)), Ok(9));
+        assert_eq!(s.rewind(), Ok(()));
+        assert_eq!(s.stream_position(), Ok(0));
+        s.into_inner().into_inner().done();
+    }
+}
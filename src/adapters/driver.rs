@@ -0,0 +1,463 @@
+//! Driving [`asynch`](crate::asynch) operations from poll-based traits.
+
+use core::future::Future;
+use core::mem::ManuallyDrop;
+use core::pin::Pin;
+use core::ptr::NonNull;
+use core::task::{Context, Poll};
+
+/// Default size of the read and write buffers.
+pub(super) const DEFAULT_CAPACITY: usize = 1024;
+
+type Op<'a, E> = Pin<Box<dyn Future<Output = Outcome<E>> + 'a>>;
+
+/// Result of an operation.
+enum Outcome<E> {
+    Read(Result<usize, E>),
+    Write(Result<usize, E>),
+    Flush(Result<(), E>),
+    Seek(Result<u64, E>),
+}
+
+/// Which of the driver's buffers an operation borrows.
+#[derive(Clone, Copy)]
+enum Buf {
+    None,
+    Read,
+    Write,
+}
+
+/// Erase the lifetime of an operation, to store it in the driver.
+///
+/// # Safety
+///
+/// The operation must be dropped before anything it borrows.
+unsafe fn erase<E>(op: Op<'_, E>) -> Op<'static, E> {
+    core::mem::transmute(op)
+}
+
+/// Results of operations completed while the caller was polling for another one.
+struct Done<E> {
+    read: Option<Result<usize, E>>,
+    write: Option<Result<usize, E>>,
+    flush: Option<Result<(), E>>,
+    seek: Option<Result<u64, E>>,
+}
+
+/// Runs operations on an [`asynch`](crate::asynch) object one at a time, keeping the
+/// in-flight operation's future across polls.
+///
+/// The futures borrow the object and a buffer for the whole operation, while poll-based
+/// traits may pass a different buffer on each poll. So the object and a read and a write
+/// buffer live on the heap, and the data is copied in and out.
+pub(super) struct Driver<T: crate::Io> {
+    /// In-flight operation. It borrows `inner` and at most one of the buffers, so it's erased
+    /// to `'static` and must be dropped before them.
+    op: Option<Op<'static, T::Error>>,
+    done: Done<T::Error>,
+    /// Heap-allocated, so the in-flight operation stays valid when the driver moves. Raw
+    /// pointers rather than `Box`es, as a `Box` asserts unique access when moved.
+    inner: NonNull<T>,
+    read_buf: NonNull<[u8]>,
+    write_buf: NonNull<[u8]>,
+    read_pos: usize,
+    read_len: usize,
+}
+
+// Nothing is structurally pinned: the in-flight operation is boxed, and what it borrows is on
+// the heap.
+impl<T: crate::Io> Unpin for Driver<T> {}
+
+impl<T: crate::Io> Driver<T> {
+    /// Create a new driver, with read and write buffers of `capacity` bytes each.
+    pub(super) fn new(inner: T, capacity: usize) -> Self {
+        assert!(capacity != 0);
+        let buf = || NonNull::from(Box::leak(vec![0; capacity].into_boxed_slice()));
+        Self {
+            op: None,
+            done: Done {
+                read: None,
+                write: None,
+                flush: None,
+                seek: None,
+            },
+            inner: NonNull::from(Box::leak(Box::new(inner))),
+            read_buf: buf(),
+            write_buf: buf(),
+            read_pos: 0,
+            read_len: 0,
+        }
+    }
+
+    pub(super) fn into_inner(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        this.op = None;
+        // Safety: the operation borrowing them is gone. They were leaked from `Box`es in `new`,
+        // and `this` isn't dropped, so each is reclaimed once.
+        unsafe {
+            drop(core::ptr::read(&this.done));
+            drop(Box::from_raw(this.read_buf.as_ptr()));
+            drop(Box::from_raw(this.write_buf.as_ptr()));
+            *Box::from_raw(this.inner.as_ptr())
+        }
+    }
+
+    /// Cancel any in-flight operation, and borrow the inner object.
+    pub(super) fn inner_mut(&mut self) -> &mut T {
+        self.op = None;
+        // Safety: the operation borrowing it is gone, and no other starts while the returned
+        // borrow of `self` lives.
+        unsafe { self.inner.as_mut() }
+    }
+
+    /// Start an operation, which borrows the inner object and the buffer `buf` selects. There
+    /// must be none in flight.
+    fn start<'s>(
+        &'s mut self,
+        buf: Buf,
+        make: impl FnOnce(&'s mut T, &'s mut [u8]) -> Op<'s, T::Error>,
+    ) {
+        debug_assert!(self.op.is_none());
+        // Safety: the pointees are separate heap allocations, which live until the driver is
+        // dropped. The operation is their only user until it's dropped: the driver doesn't
+        // touch the inner object while it's in flight, and only touches the buffer it doesn't
+        // borrow, or none at all for reads, see `buffered`.
+        let (inner, buf) = unsafe {
+            let buf: &'s mut [u8] = match buf {
+                Buf::None => &mut [],
+                Buf::Read => &mut *self.read_buf.as_ptr(),
+                Buf::Write => &mut *self.write_buf.as_ptr(),
+            };
+            (&mut *self.inner.as_ptr(), buf)
+        };
+        let op = make(inner, buf);
+        // Safety: the operation is dropped before the driver frees what it borrows, in `Drop`
+        // and `into_inner`, and before handing out the inner object in `inner_mut`.
+        self.op = Some(unsafe { erase(op) });
+    }
+
+    /// Data read from the inner object but not yet returned.
+    fn buffered(&self) -> &[u8] {
+        if self.read_pos == self.read_len {
+            // A read may be in flight, borrowing the buffer.
+            return &[];
+        }
+        // Safety: data is buffered only once the read that filled the buffer completed, and
+        // the next read starts only once it's all returned. Other operations don't borrow the
+        // read buffer.
+        unsafe { &self.read_buf.as_ref()[self.read_pos..self.read_len] }
+    }
+
+    /// Drive the in-flight operation, if any, to completion, storing its result.
+    fn drive(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if let Some(op) = &mut self.op {
+            let outcome = match op.as_mut().poll(cx) {
+                Poll::Ready(outcome) => outcome,
+                Poll::Pending => return Poll::Pending,
+            };
+            self.op = None;
+            match outcome {
+                Outcome::Read(res) => self.done.read = Some(res),
+                Outcome::Write(res) => self.done.write = Some(res),
+                Outcome::Flush(res) => self.done.flush = Some(res),
+                Outcome::Seek(res) => self.done.seek = Some(res),
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+impl<T: crate::Io> Drop for Driver<T> {
+    fn drop(&mut self) {
+        self.op = None;
+        // Safety: the operation borrowing them is gone, and they were leaked from `Box`es in
+        // `new`.
+        unsafe {
+            drop(Box::from_raw(self.read_buf.as_ptr()));
+            drop(Box::from_raw(self.write_buf.as_ptr()));
+            drop(Box::from_raw(self.inner.as_ptr()));
+        }
+    }
+}
+
+impl<T: crate::asynch::Read> Driver<T> {
+    pub(super) fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, T::Error>> {
+        loop {
+            let data = self.buffered();
+            if !data.is_empty() {
+                let n = data.len().min(buf.len());
+                buf[..n].copy_from_slice(&data[..n]);
+                self.read_pos += n;
+                return Poll::Ready(Ok(n));
+            }
+            match self.done.read.take() {
+                // Buffered even if `buf` is empty, to be returned by the next reads.
+                Some(Ok(n)) if n != 0 => {
+                    self.read_pos = 0;
+                    self.read_len = n;
+                    continue;
+                }
+                Some(res) => return Poll::Ready(res),
+                None => {}
+            }
+            if self.drive(cx).is_pending() {
+                return Poll::Pending;
+            }
+            if self.done.read.is_none() {
+                if buf.is_empty() {
+                    return Poll::Ready(Ok(0));
+                }
+                let len = buf.len().min(self.read_buf.len());
+                self.start(Buf::Read, |inner, read_buf| {
+                    Box::pin(async move { Outcome::Read(inner.read(&mut read_buf[..len]).await) })
+                });
+            }
+        }
+    }
+}
+
+impl<T: crate::asynch::Write> Driver<T> {
+    pub(super) fn poll_write(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, T::Error>> {
+        loop {
+            if let Some(res) = self.done.write.take() {
+                return Poll::Ready(res);
+            }
+            if self.drive(cx).is_pending() {
+                return Poll::Pending;
+            }
+            if self.done.write.is_none() {
+                if buf.is_empty() {
+                    return Poll::Ready(Ok(0));
+                }
+                let len = buf.len().min(self.write_buf.len());
+                self.start(Buf::Write, |inner, write_buf| {
+                    write_buf[..len].copy_from_slice(&buf[..len]);
+                    Box::pin(async move { Outcome::Write(inner.write(&write_buf[..len]).await) })
+                });
+            }
+        }
+    }
+
+    pub(super) fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), T::Error>> {
+        loop {
+            if let Some(res) = self.done.flush.take() {
+                return Poll::Ready(res);
+            }
+            if self.drive(cx).is_pending() {
+                return Poll::Pending;
+            }
+            if self.done.flush.is_none() {
+                self.start(Buf::None, |inner, _| {
+                    Box::pin(async move { Outcome::Flush(inner.flush().await) })
+                });
+            }
+        }
+    }
+}
+
+impl<T: crate::asynch::Seek> Driver<T> {
+    /// Seek to `pos`. Polls while the seek is in flight must pass the same position.
+    pub(super) fn poll_seek(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut pos: crate::SeekFrom,
+    ) -> Poll<Result<u64, T::Error>> {
+        loop {
+            if let Some(res) = self.done.seek.take() {
+                return Poll::Ready(res);
+            }
+            if self.drive(cx).is_pending() {
+                return Poll::Pending;
+            }
+            if self.done.seek.is_none() {
+                // A completed read's data counts as buffered. An error from it is dropped, as
+                // the seek moves past wherever it happened.
+                if let Some(Ok(n)) = self.done.read.take() {
+                    self.read_pos = 0;
+                    self.read_len = n;
+                }
+                // The inner reader is ahead of the caller by the buffered data.
+                if let crate::SeekFrom::Current(n) = &mut pos {
+                    *n -= (self.read_len - self.read_pos) as i64;
+                }
+                self.read_pos = 0;
+                self.read_len = 0;
+                self.start(Buf::None, |inner, _| {
+                    Box::pin(async move { Outcome::Seek(inner.seek(pos).await) })
+                });
+            }
+        }
+    }
+}
+
+// These tests avoid tokio and threads, so they run under `cargo miri test`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::block_on::noop_waker;
+    use crate::chaos::{Chaotic, Fault};
+    use crate::mock::{Mock, Transaction};
+    use crate::{ErrorKind, SeekFrom};
+
+    type Chaos = Chaotic<Mock, std::vec::IntoIter<Fault>>;
+
+    fn driver(script: impl IntoIterator<Item = Transaction>, faults: Vec<Fault>) -> Driver<Chaos> {
+        Driver::new(Chaotic::new(Mock::new(script), faults.into_iter()), 8)
+    }
+
+    #[test]
+    fn buffered_read_with_write_in_flight() {
+        let waker = noop_waker();
+        let cx = &mut Context::from_waker(&waker);
+        let mut d = driver(
+            [Transaction::read(b"abcd"), Transaction::write(b"xy")],
+            vec![Fault::NONE.with_pending(1), Fault::NONE.with_pending(1)],
+        );
+
+        let mut buf = [0; 4];
+        assert!(d.poll_read(cx, &mut buf).is_pending());
+        // The read completes with 4 bytes, 2 of which are buffered.
+        assert_eq!(d.poll_read(cx, &mut buf[..2]), Poll::Ready(Ok(2)));
+        assert_eq!(&buf[..2], b"ab");
+
+        // The write borrows the write buffer only, so the buffered data can be read meanwhile.
+        assert!(d.poll_write(cx, b"xy").is_pending());
+        assert_eq!(d.poll_read(cx, &mut buf), Poll::Ready(Ok(2)));
+        assert_eq!(&buf[..2], b"cd");
+        assert_eq!(d.poll_write(cx, b"xy"), Poll::Ready(Ok(2)));
+        d.into_inner().into_inner().done();
+    }
+
+    #[test]
+    fn write_copies_the_data() {
+        let waker = noop_waker();
+        let cx = &mut Context::from_waker(&waker);
+        let mut d = driver(
+            [Transaction::write(b"abcdefgh"), Transaction::Flush],
+            vec![Fault::NONE.with_pending(2)],
+        );
+        // The data is copied when the write starts, later polls may pass another buffer.
+        assert!(d.poll_write(cx, b"abcdefghij").is_pending());
+        assert!(d.poll_write(cx, b"").is_pending());
+        assert_eq!(d.poll_write(cx, b""), Poll::Ready(Ok(8)));
+        assert_eq!(d.poll_flush(cx), Poll::Ready(Ok(())));
+        d.into_inner().into_inner().done();
+    }
+
+    #[test]
+    fn results_are_kept_for_their_operation() {
+        let waker = noop_waker();
+        let cx = &mut Context::from_waker(&waker);
+        let mut d = driver(
+            [
+                Transaction::write(b"a"),
+                Transaction::Seek(SeekFrom::Start(3), 3),
+            ],
+            vec![Fault::NONE.with_pending(1), Fault::NONE],
+        );
+        assert!(d.poll_write(cx, b"a").is_pending());
+        // The seek waits for the write to complete, which keeps its result.
+        assert_eq!(d.poll_seek(cx, SeekFrom::Start(3)), Poll::Ready(Ok(3)));
+        assert_eq!(d.poll_write(cx, b"a"), Poll::Ready(Ok(1)));
+        d.into_inner().into_inner().done();
+    }
+
+    #[test]
+    fn read_into_empty_buf() {
+        let waker = noop_waker();
+        let cx = &mut Context::from_waker(&waker);
+        let mut d = driver(
+            [Transaction::read(b"abcd")],
+            vec![Fault::NONE.with_pending(1)],
+        );
+        let mut buf = [0; 4];
+        assert!(d.poll_read(cx, &mut buf).is_pending());
+        // The read completes, and its data is kept for the next read.
+        assert_eq!(d.poll_read(cx, &mut []), Poll::Ready(Ok(0)));
+        assert_eq!(d.poll_read(cx, &mut buf), Poll::Ready(Ok(4)));
+        assert_eq!(&buf, b"abcd");
+        d.into_inner().into_inner().done();
+    }
+
+    #[test]
+    fn read_then_seek() {
+        let waker = noop_waker();
+        let cx = &mut Context::from_waker(&waker);
+        let mut d = driver(
+            [
+                Transaction::read(b"abcd"),
+                Transaction::Seek(SeekFrom::Current(-4), 0),
+                Transaction::read(b"ab"),
+                Transaction::Seek(SeekFrom::Current(-1), 1),
+            ],
+            vec![
+                Fault::NONE.with_pending(1),
+                Fault::NONE,
+                Fault::NONE.with_pending(1),
+            ],
+        );
+        let mut buf = [0; 4];
+        // The read completes while the seek is polled, so none of its data is returned.
+        assert!(d.poll_read(cx, &mut buf).is_pending());
+        assert_eq!(d.poll_seek(cx, SeekFrom::Current(0)), Poll::Ready(Ok(0)));
+
+        // Buffered data is accounted for too.
+        assert!(d.poll_read(cx, &mut buf[..2]).is_pending());
+        assert_eq!(d.poll_read(cx, &mut buf[..1]), Poll::Ready(Ok(1)));
+        assert_eq!(d.poll_seek(cx, SeekFrom::Current(0)), Poll::Ready(Ok(1)));
+        d.into_inner().into_inner().done();
+    }
+
+    #[test]
+    fn errors() {
+        let waker = noop_waker();
+        let cx = &mut Context::from_waker(&waker);
+        let mut d = driver(
+            [Transaction::Error(ErrorKind::TimedOut)],
+            vec![Fault::NONE, Fault::error(ErrorKind::Other)],
+        );
+        let mut buf = [0; 4];
+        assert_eq!(
+            d.poll_read(cx, &mut buf),
+            Poll::Ready(Err(ErrorKind::TimedOut.into()))
+        );
+        assert_eq!(
+            d.poll_flush(cx),
+            Poll::Ready(Err(crate::chaos::ChaoticError::Injected(ErrorKind::Other)))
+        );
+    }
+
+    #[test]
+    fn cancel_in_flight() {
+        let waker = noop_waker();
+        let cx = &mut Context::from_waker(&waker);
+        let mut d = driver(
+            [Transaction::write(b"ab"), Transaction::read(b"cd")],
+            vec![Fault::NONE.with_pending(1), Fault::NONE.with_pending(1)],
+        );
+        assert!(d.poll_write(cx, b"ab").is_pending());
+        // Borrowing the inner object cancels the write.
+        d.inner_mut();
+        let mut buf = [0; 2];
+        assert!(d.poll_read(cx, &mut buf).is_pending());
+        // Dropping the driver or taking the inner object cancels the read.
+        drop(d);
+
+        let mut d = driver(
+            [Transaction::read(b"cd")],
+            vec![Fault::NONE.with_pending(1)],
+        );
+        assert!(d.poll_read(cx, &mut buf).is_pending());
+        let mut chaos = d.into_inner();
+        assert_eq!(crate::blocking::Read::read(&mut chaos, &mut buf), Ok(2));
+        chaos.into_inner().done();
+    }
+}
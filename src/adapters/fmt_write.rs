@@ -0,0 +1,327 @@
+use core::fmt;
+
+use crate::blocking::WriteAllError;
+
+/// Adapter to [`core::fmt::Write`].
+///
+/// Each `write_str` writes the whole string with
+/// [`write_all`](crate::blocking::Write::write_all). Since [`fmt::Error`] carries no
+/// information, an error from the inner writer is stored, and can be retrieved with
+/// [`take_error`](Self::take_error).
+pub struct ToFmt<T: crate::Io> {
+    inner: T,
+    error: Option<WriteAllError<T::Error>>,
+}
+
+impl<T: crate::Io> ToFmt<T> {
+    /// Create a new adapter.
+    pub fn new(inner: T) -> Self {
+        Self { inner, error: None }
+    }
+
+    /// Consume the adapter, returning the inner object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Take the error from the last `write_str` that failed, if any.
+    pub fn take_error(&mut self) -> Option<WriteAllError<T::Error>> {
+        self.error.take()
+    }
+}
+
+impl<T: crate::blocking::Write> fmt::Write for ToFmt<T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.inner.write_all(s.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.error = Some(e);
+                Err(fmt::Error)
+            }
+        }
+    }
+}
+
+/// Error returned by [`FromFmt`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FromFmtError {
+    /// The data written isn't valid UTF-8.
+    InvalidUtf8,
+    /// The inner writer returned [`fmt::Error`].
+    Fmt,
+}
+
+impl crate::Error for FromFmtError {
+    fn kind(&self) -> crate::ErrorKind {
+        match self {
+            Self::InvalidUtf8 => crate::ErrorKind::InvalidData,
+            Self::Fmt => crate::ErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for FromFmtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FromFmtError {}
+
+/// Adapter from [`core::fmt::Write`].
+///
+/// Writes pass the data to the inner writer as strings, so it must be valid UTF-8. A character
+/// may be split across writes: its first bytes are kept until the rest arrives. Writing invalid
+/// UTF-8 fails with [`FromFmtError::InvalidUtf8`], after any valid data before it is written.
+pub struct FromFmt<W> {
+    inner: W,
+    /// Start of a character split across writes.
+    partial: [u8; 4],
+    partial_len: usize,
+}
+
+impl<W: fmt::Write> FromFmt<W> {
+    /// Create a new adapter.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            partial: [0; 4],
+            partial_len: 0,
+        }
+    }
+
+    /// Consume the adapter, returning the inner object.
+    ///
+    /// The start of a character split across writes is lost.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    fn write_bytes(&mut self, mut buf: &[u8]) -> Result<usize, FromFmtError> {
+        let len = buf.len();
+
+        if self.partial_len > 0 {
+            let width = utf8_width(self.partial[0]);
+            let amt = core::cmp::min(width - self.partial_len, buf.len());
+            self.partial[self.partial_len..][..amt].copy_from_slice(&buf[..amt]);
+            let partial = &self.partial[..self.partial_len + amt];
+            match core::str::from_utf8(partial) {
+                Ok(s) => {
+                    self.partial_len = 0;
+                    self.inner.write_str(s).map_err(|_| FromFmtError::Fmt)?;
+                }
+                Err(e) if e.error_len().is_none() => {
+                    self.partial_len = partial.len();
+                    return Ok(amt);
+                }
+                Err(_) => {
+                    self.partial_len = 0;
+                    return Err(FromFmtError::InvalidUtf8);
+                }
+            }
+            buf = &buf[amt..];
+        }
+
+        let (valid, rest) = match core::str::from_utf8(buf) {
+            Ok(s) => (s, &[][..]),
+            Err(e) => {
+                let (valid, rest) = buf.split_at(e.valid_up_to());
+                // Safety: `from_utf8` checked this part.
+                let valid = unsafe { core::str::from_utf8_unchecked(valid) };
+                if e.error_len().is_some() {
+                    // Write the valid part, and fail on the next write.
+                    if valid.is_empty() && buf.len() == len {
+                        return Err(FromFmtError::InvalidUtf8);
+                    }
+                    self.inner.write_str(valid).map_err(|_| FromFmtError::Fmt)?;
+                    return Ok(len - rest.len());
+                }
+                (valid, rest)
+            }
+        };
+        self.inner.write_str(valid).map_err(|_| FromFmtError::Fmt)?;
+        self.partial[..rest.len()].copy_from_slice(rest);
+        self.partial_len = rest.len();
+        Ok(len)
+    }
+}
+
+/// Length of the UTF-8 sequence starting with `byte`, which must be a valid first byte of a
+/// multi-byte sequence.
+fn utf8_width(byte: u8) -> usize {
+    match byte {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
+
+impl<W> crate::Io for FromFmt<W> {
+    type Error = FromFmtError;
+}
+
+impl<W: fmt::Write> crate::blocking::Write for FromFmt<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.write_bytes(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<W: fmt::Write> crate::asynch::Write for FromFmt<W> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.write_bytes(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::Write;
+    use core::fmt::Write as _;
+
+    /// Fixed-capacity `fmt::Write` that fails when full.
+    struct Out {
+        buf: [u8; 16],
+        len: usize,
+    }
+
+    impl Out {
+        fn new() -> Self {
+            Self {
+                buf: [0; 16],
+                len: 0,
+            }
+        }
+
+        fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.buf[..self.len]).unwrap()
+        }
+    }
+
+    impl fmt::Write for Out {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            let dst = self.buf[self.len..].get_mut(..s.len()).ok_or(fmt::Error)?;
+            dst.copy_from_slice(s.as_bytes());
+            self.len += s.len();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn to_fmt() {
+        let mut buf = [0; 4];
+        let mut w = ToFmt::new(&mut buf[..]);
+        assert_eq!(write!(w, "{}", 12), Ok(()));
+        assert_eq!(w.take_error(), None);
+        assert_eq!(write!(w, "{}", 345), Err(fmt::Error));
+        assert_eq!(w.take_error(), Some(WriteAllError::WriteZero));
+        assert_eq!(w.take_error(), None);
+        assert_eq!(buf, *b"1234");
+    }
+
+    #[test]
+    fn split_chars() {
+        for c in ['é', '€', '😀'] {
+            let mut encoded = [0; 4];
+            let bytes = c.encode_utf8(&mut encoded).as_bytes();
+            let mut w = FromFmt::new(Out::new());
+            for byte in bytes {
+                assert_eq!(w.write(core::slice::from_ref(byte)), Ok(1));
+            }
+            assert_eq!(w.inner().as_str().chars().next(), Some(c));
+        }
+
+        // The rest of a character, followed by more data.
+        let mut w = FromFmt::new(Out::new());
+        assert_eq!(w.write(b"a\xF0\x9F"), Ok(3));
+        assert_eq!(w.inner().as_str(), "a");
+        assert_eq!(w.write(b"\x98"), Ok(1));
+        assert_eq!(w.write(b"\x80b\xE2\x82"), Ok(4));
+        assert_eq!(w.inner().as_str(), "a😀b");
+        assert_eq!(w.write(b"\xACc"), Ok(2));
+        assert_eq!(w.inner().as_str(), "a😀b€c");
+    }
+
+    #[test]
+    fn invalid_after_valid_prefix() {
+        let mut w = FromFmt::new(Out::new());
+        assert_eq!(w.write(b"ab\xFFcd"), Ok(2));
+        assert_eq!(w.write(b"\xFFcd"), Err(FromFmtError::InvalidUtf8));
+        assert_eq!(w.write(b"cd"), Ok(2));
+        assert_eq!(w.inner().as_str(), "abcd");
+
+        let mut w = FromFmt::new(Out::new());
+        assert_eq!(
+            w.write_all(b"xy\xC3("),
+            Err(WriteAllError::Other(FromFmtError::InvalidUtf8))
+        );
+        assert_eq!(w.inner().as_str(), "xy");
+    }
+
+    #[test]
+    fn invalid_continuation() {
+        // The start of a character, followed by a byte that can't continue it.
+        let mut w = FromFmt::new(Out::new());
+        assert_eq!(w.write(b"\xE2\x82"), Ok(2));
+        assert_eq!(w.write(b"a"), Err(FromFmtError::InvalidUtf8));
+        // The partial character is dropped, and the byte wasn't consumed.
+        assert_eq!(w.write(b"a"), Ok(1));
+        assert_eq!(w.inner().as_str(), "a");
+
+        // A character completed by the start of a write that then turns invalid.
+        let mut w = FromFmt::new(Out::new());
+        assert_eq!(w.write(b"\xC3"), Ok(1));
+        assert_eq!(w.write(b"\xA9\xFF"), Ok(1));
+        assert_eq!(w.write(b"\xFF"), Err(FromFmtError::InvalidUtf8));
+        assert_eq!(w.inner().as_str(), "é");
+    }
+
+    #[test]
+    fn inner_errors() {
+        let mut w = FromFmt::new(Out::new());
+        assert_eq!(w.write(&[b'a'; 16]), Ok(16));
+        assert_eq!(w.write(b"b"), Err(FromFmtError::Fmt));
+        assert_eq!(w.write(b""), Ok(0));
+        assert_eq!(w.flush(), Ok(()));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+
+        let mut w = FromFmt::new(Out::new());
+        let res = block_on(crate::asynch::Write::write(&mut w, b"ok\xC3"), &mut Spin);
+        assert_eq!(res, Ok(3));
+        let res = block_on(crate::asynch::Write::write(&mut w, b"\xA9"), &mut Spin);
+        assert_eq!(res, Ok(1));
+        assert_eq!(w.inner().as_str(), "oké");
+    }
+}
@@ -0,0 +1,374 @@
+use crate::asynch::yield_n;
+
+/// Adapter from [`blocking`](crate::blocking) traits to [`asynch`](crate::asynch) traits.
+///
+/// Operations run the blocking call directly, so they complete the first time they're polled,
+/// blocking the executor for as long as the call takes. This suits objects that never block
+/// for long, such as a semihosting console or a local file. For anything else, see
+/// [`FromBlockingThreaded`].
+pub struct FromBlocking<T> {
+    inner: T,
+    yielding: bool,
+}
+
+impl<T> FromBlocking<T> {
+    /// Create a new adapter.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            yielding: false,
+        }
+    }
+
+    /// Yield to the executor once before each operation, so that a task looping on the adapter
+    /// doesn't starve other tasks.
+    pub fn yielding(mut self) -> Self {
+        self.yielding = true;
+        self
+    }
+
+    /// Consume the adapter, returning the inner object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    async fn maybe_yield(&self) {
+        if self.yielding {
+            yield_n(1).await;
+        }
+    }
+}
+
+impl<T: crate::Io> crate::Io for FromBlocking<T> {
+    type Error = T::Error;
+}
+
+impl<T: crate::blocking::Read> crate::asynch::Read for FromBlocking<T> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.maybe_yield().await;
+        self.inner.read(buf)
+    }
+}
+
+impl<T: crate::blocking::BufRead> crate::asynch::BufRead for FromBlocking<T> {
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        self.maybe_yield().await;
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+impl<T: crate::blocking::Write> crate::asynch::Write for FromBlocking<T> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.maybe_yield().await;
+        self.inner.write(buf)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.maybe_yield().await;
+        self.inner.flush()
+    }
+}
+
+impl<T: crate::blocking::Seek> crate::asynch::Seek for FromBlocking<T> {
+    async fn seek(&mut self, pos: crate::SeekFrom) -> Result<u64, Self::Error> {
+        self.maybe_yield().await;
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(feature = "std")]
+pub use threaded::*;
+
+#[cfg(feature = "std")]
+mod threaded {
+    use core::task::{Poll, Waker};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread::JoinHandle;
+
+    type Job<T> = Box<dyn FnOnce(&mut T) + Send>;
+
+    enum State<R> {
+        Pending(Option<Waker>),
+        Done(R),
+        /// The job was dropped without completing, because the worker panicked.
+        Abandoned,
+        Taken,
+    }
+
+    /// Completes the operation from the worker thread.
+    struct Completion<R> {
+        state: Arc<Mutex<State<R>>>,
+    }
+
+    impl<R> Completion<R> {
+        /// Set the state if the operation is still pending, waking its task.
+        fn set(&self, new: State<R>) {
+            let mut state = self.state.lock().unwrap();
+            if let State::Pending(waker) = &mut *state {
+                let waker = waker.take();
+                *state = new;
+                drop(state);
+                if let Some(waker) = waker {
+                    waker.wake();
+                }
+            }
+        }
+
+        fn complete(self, res: R) {
+            self.set(State::Done(res));
+        }
+    }
+
+    impl<R> Drop for Completion<R> {
+        fn drop(&mut self) {
+            self.set(State::Abandoned);
+        }
+    }
+
+    /// Adapter from [`blocking`](crate::blocking) traits to [`asynch`](crate::asynch) traits,
+    /// running the blocking calls on a worker thread.
+    ///
+    /// The inner object is moved to a thread owned by the adapter. Each operation sends the
+    /// call to the thread, and wakes the task once it completes, so the executor isn't blocked.
+    /// Data is copied to and from the thread.
+    ///
+    /// If an operation's future is dropped before completing, the call still runs to
+    /// completion on the thread and its result is discarded: data read is lost, and data
+    /// written is still written.
+    ///
+    /// # Panics
+    ///
+    /// If a call panics on the worker thread, that operation and all later ones panic.
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub struct FromBlockingThreaded<T> {
+        jobs: mpsc::Sender<Job<T>>,
+        worker: JoinHandle<T>,
+    }
+
+    impl<T: Send + 'static> FromBlockingThreaded<T> {
+        /// Create a new adapter, spawning its worker thread.
+        pub fn new(inner: T) -> Self {
+            let (jobs, rx) = mpsc::channel::<Job<T>>();
+            let worker = std::thread::spawn(move || {
+                let mut inner = inner;
+                for job in rx {
+                    job(&mut inner);
+                }
+                inner
+            });
+            Self { jobs, worker }
+        }
+
+        /// Consume the adapter, returning the inner object.
+        ///
+        /// This blocks until the operations still running on the worker thread complete.
+        pub fn into_inner(self) -> T {
+            drop(self.jobs);
+            match self.worker.join() {
+                Ok(inner) => inner,
+                Err(e) => std::panic::resume_unwind(e),
+            }
+        }
+
+        /// Run `f` on the worker thread.
+        async fn run<R: Send + 'static>(
+            &mut self,
+            f: impl FnOnce(&mut T) -> R + Send + 'static,
+        ) -> R {
+            let state = Arc::new(Mutex::new(State::Pending(None)));
+            let completion = Completion {
+                state: state.clone(),
+            };
+            let job: Job<T> = Box::new(move |inner| completion.complete(f(inner)));
+            if self.jobs.send(job).is_err() {
+                panic!("worker thread panicked");
+            }
+
+            core::future::poll_fn(|cx| {
+                let mut state = state.lock().unwrap();
+                match core::mem::replace(&mut *state, State::Taken) {
+                    State::Done(res) => Poll::Ready(res),
+                    State::Pending(_) => {
+                        *state = State::Pending(Some(cx.waker().clone()));
+                        Poll::Pending
+                    }
+                    State::Abandoned => panic!("worker thread panicked"),
+                    State::Taken => unreachable!(),
+                }
+            })
+            .await
+        }
+    }
+
+    impl<T: crate::Io> crate::Io for FromBlockingThreaded<T> {
+        type Error = T::Error;
+    }
+
+    impl<T> crate::asynch::Read for FromBlockingThreaded<T>
+    where
+        T: crate::blocking::Read + Send + 'static,
+        T::Error: Send,
+    {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let len = buf.len();
+            let (data, res) = self
+                .run(move |inner| {
+                    let mut data = vec![0; len];
+                    let res = inner.read(&mut data);
+                    (data, res)
+                })
+                .await;
+            if let Ok(n) = res {
+                buf[..n].copy_from_slice(&data[..n]);
+            }
+            res
+        }
+    }
+
+    impl<T> crate::asynch::Write for FromBlockingThreaded<T>
+    where
+        T: crate::blocking::Write + Send + 'static,
+        T::Error: Send,
+    {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            let data = buf.to_vec();
+            self.run(move |inner| inner.write(&data)).await
+        }
+
+        async fn flush(&mut self) -> Result<(), Self::Error> {
+            self.run(|inner| inner.flush()).await
+        }
+    }
+
+    impl<T> crate::asynch::Seek for FromBlockingThreaded<T>
+    where
+        T: crate::blocking::Seek + Send + 'static,
+        T::Error: Send,
+    {
+        async fn seek(&mut self, pos: crate::SeekFrom) -> Result<u64, Self::Error> {
+            self.run(move |inner| inner.seek(pos)).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::block_on;
+    use crate::asynch::{BufRead, Read, Write};
+
+    #[test]
+    fn completes_immediately() {
+        let mut polls = 0;
+        let mut buf = [0; 8];
+        let mut r = FromBlocking::new(&b"abcdef"[..]);
+        assert_eq!(block_on(r.read(&mut buf[..2]), &mut || polls += 1), Ok(2));
+        assert_eq!(block_on(r.fill_buf(), &mut || polls += 1), Ok(&b"cdef"[..]));
+        r.consume(4);
+        assert_eq!(block_on(r.read(&mut buf), &mut || polls += 1), Ok(0));
+
+        let mut w = FromBlocking::new(&mut buf[..3]);
+        assert_eq!(block_on(w.write(b"xyzw"), &mut || polls += 1), Ok(3));
+        assert_eq!(block_on(w.flush(), &mut || polls += 1), Ok(()));
+        assert_eq!(polls, 0);
+        assert_eq!(&buf[..3], b"xyz");
+    }
+
+    #[test]
+    fn yielding() {
+        let mut polls = 0;
+        let mut r = FromBlocking::new(&b"ab"[..]).yielding();
+        let mut buf = [0; 1];
+        assert_eq!(block_on(r.read(&mut buf), &mut || polls += 1), Ok(1));
+        assert_eq!(block_on(r.fill_buf(), &mut || polls += 1), Ok(&b"b"[..]));
+        assert_eq!(polls, 2);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn seek_and_errors() {
+        use crate::adapters::Spin;
+        use crate::asynch::Seek;
+        use crate::mock::{Mock, Transaction};
+        use crate::{ErrorKind, SeekFrom};
+
+        let mock = Mock::new([
+            Transaction::Seek(SeekFrom::Start(4), 4),
+            Transaction::Error(ErrorKind::TimedOut),
+        ]);
+        let mut s = FromBlocking::new(mock);
+        assert_eq!(block_on(s.seek(SeekFrom::Start(4)), &mut Spin), Ok(4));
+        assert_eq!(block_on(s.write(b"a"), &mut Spin), Err(ErrorKind::TimedOut));
+        s.into_inner().done();
+    }
+
+    #[cfg(feature = "std")]
+    mod threaded {
+        use core::future::Future;
+        use core::pin::pin;
+        use core::task::Context;
+
+        use super::*;
+        use crate::adapters::Spin;
+        use crate::asynch::Seek;
+        use crate::mock::{Mock, Transaction};
+        use crate::{ErrorKind, SeekFrom};
+
+        #[test]
+        fn operations() {
+            let mock = Mock::new([
+                Transaction::write(b"ping"),
+                Transaction::Flush,
+                Transaction::read(b"pong"),
+                Transaction::Seek(SeekFrom::Current(-4), 0),
+                Transaction::Error(ErrorKind::InvalidData),
+            ]);
+            let mut t = FromBlockingThreaded::new(mock);
+            let mut buf = [0; 8];
+            assert_eq!(block_on(t.write(b"ping"), &mut Spin), Ok(4));
+            assert_eq!(block_on(t.flush(), &mut Spin), Ok(()));
+            assert_eq!(block_on(t.read(&mut buf), &mut Spin), Ok(4));
+            assert_eq!(&buf[..4], b"pong");
+            assert_eq!(block_on(t.seek(SeekFrom::Current(-4)), &mut Spin), Ok(0));
+            assert_eq!(
+                block_on(t.read(&mut buf), &mut Spin),
+                Err(ErrorKind::InvalidData)
+            );
+            t.into_inner().done();
+        }
+
+        #[test]
+        fn dropped_operation_still_runs() {
+            let mock = Mock::new([Transaction::write(b"ab")]);
+            let mut t = FromBlockingThreaded::new(mock);
+            {
+                let waker = crate::adapters::block_on::noop_waker();
+                let fut = pin!(t.write(b"ab"));
+                let _ = fut.poll(&mut Context::from_waker(&waker));
+            }
+            // `into_inner` waits for the write.
+            t.into_inner().done();
+        }
+
+        #[test]
+        #[should_panic(expected = "worker thread panicked")]
+        fn worker_panic() {
+            let mut t = FromBlockingThreaded::new(Mock::new([]));
+            let _ = block_on(t.flush(), &mut Spin);
+        }
+    }
+}
@@ -1,7 +1,11 @@
 use core::pin::Pin;
+use core::task::{Context, Poll};
 
 use futures::future::poll_fn;
 
+use super::driver::{Driver, DEFAULT_CAPACITY};
+use super::to_io_error;
+
 /// Adapter from `futures::io` traits.
 #[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
 #[derive(Clone)]
@@ -43,6 +47,16 @@ impl<T: futures::io::AsyncRead + Unpin + ?Sized> crate::asynch::Read for FromFut
     }
 }
 
+impl<T: futures::io::AsyncBufRead + Unpin + ?Sized> crate::asynch::BufRead for FromFutures<T> {
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        futures::io::AsyncBufReadExt::fill_buf(&mut self.inner).await
+    }
+
+    fn consume(&mut self, amt: usize) {
+        Pin::new(&mut self.inner).consume(amt)
+    }
+}
+
 impl<T: futures::io::AsyncWrite + Unpin + ?Sized> crate::asynch::Write for FromFutures<T> {
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
         poll_fn(|cx| Pin::new(&mut self.inner).poll_write(cx, buf)).await
@@ -59,8 +73,239 @@ impl<T: futures::io::AsyncSeek + Unpin + ?Sized> crate::asynch::Seek for FromFut
     }
 }
 
-// TODO: ToFutures.
-// It's a bit tricky because futures::io is "stateless", while we're "stateful" (we
-// return futures that borrow Self and get polled for the duration of the operation.)
-// It can probably done by storing the futures in Self, with unsafe Pin hacks because
-// we're a self-referential struct
+/// Adapter to `futures::io` traits.
+///
+/// The futures returned by [`asynch`](crate::asynch) traits borrow the object and the buffer
+/// for the whole operation, while `futures::io` traits are polled with a possibly different
+/// buffer each time. To bridge the two, the adapter owns the inner object and a read and a
+/// write buffer on the heap, and keeps the in-flight operation's future across polls.
+///
+/// # Buffer semantics
+///
+/// - Reads go through the adapter's read buffer. The amount requested from the inner reader
+///   is the length of the buffer passed to the poll that starts the operation. If a later
+///   poll passes a shorter buffer, the excess is kept and returned by the next reads.
+/// - Writes copy the data passed to the poll that starts the operation into the adapter's
+///   write buffer. Polls while that write is in flight must pass the same data, as the
+///   `AsyncWrite` contract requires; whatever they pass is ignored, and the result reports
+///   how much of the original data was written.
+/// - Seeking discards any buffered read data, accounting for it in [`SeekFrom::Current`].
+///
+/// Only one operation runs at a time. Polling for a different operation first drives the
+/// in-flight one to completion, and keeps its result for the next poll of that kind. This
+/// means a pending read blocks writes, so the adapter can't be used for full-duplex IO
+/// through `split`; wrap each half of the inner object separately instead.
+///
+/// Each operation allocates its future on the heap, and the adapter is neither `Send` nor
+/// `Sync`, since the futures of [`asynch`](crate::asynch) traits are not required to be.
+///
+/// [`SeekFrom::Current`]: futures::io::SeekFrom::Current
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+pub struct ToFutures<T: crate::Io> {
+    driver: Driver<T>,
+}
+
+impl<T: crate::Io> ToFutures<T> {
+    /// Create a new adapter, with the default buffer capacity.
+    pub fn new(inner: T) -> Self {
+        Self::with_capacity(inner, DEFAULT_CAPACITY)
+    }
+
+    /// Create a new adapter, with read and write buffers of `capacity` bytes each.
+    ///
+    /// This limits the amount of data each read and write transfers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(inner: T, capacity: usize) -> Self {
+        Self {
+            driver: Driver::new(inner, capacity),
+        }
+    }
+
+    /// Consume the adapter, returning the inner object.
+    ///
+    /// Any in-flight operation is cancelled, and buffered read data is lost.
+    pub fn into_inner(self) -> T {
+        self.driver.into_inner()
+    }
+
+    /// Mutably borrow the inner object.
+    ///
+    /// Any in-flight operation is cancelled first, as it borrows the inner object. Buffered
+    /// read data and results of completed operations are kept.
+    pub fn inner_mut(&mut self) -> &mut T {
+        self.driver.inner_mut()
+    }
+}
+
+impl<T: crate::asynch::Read> futures::io::AsyncRead for ToFutures<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        self.get_mut()
+            .driver
+            .poll_read(cx, buf)
+            .map_err(to_io_error)
+    }
+}
+
+impl<T: crate::asynch::Write> futures::io::AsyncWrite for ToFutures<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        self.get_mut()
+            .driver
+            .poll_write(cx, buf)
+            .map_err(to_io_error)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        self.get_mut().driver.poll_flush(cx).map_err(to_io_error)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl<T: crate::asynch::Seek> futures::io::AsyncSeek for ToFutures<T> {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: futures::io::SeekFrom,
+    ) -> Poll<Result<u64, std::io::Error>> {
+        self.get_mut()
+            .driver
+            .poll_seek(cx, pos.into())
+            .map_err(to_io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use futures::io::{AsyncReadExt, AsyncSeekExt};
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::chaos::{Chaotic, Fault};
+    use crate::mock::{Mock, Transaction};
+
+    #[test]
+    fn fill_buf() {
+        use crate::asynch::BufRead;
+
+        let inner = futures::io::BufReader::with_capacity(4, &b"abcdef"[..]);
+        let mut r = FromFutures::new(inner);
+        block_on(async {
+            assert_eq!(r.fill_buf().await.unwrap(), b"abcd");
+            r.consume(3);
+            assert_eq!(r.fill_buf().await.unwrap(), b"d");
+            r.consume(1);
+            assert_eq!(r.fill_buf().await.unwrap(), b"ef");
+            r.consume(2);
+            assert_eq!(r.fill_buf().await.unwrap(), b"");
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn round_trip() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Small buffers, so that transfers are partial.
+        let (a, mut b) = tokio::io::duplex(5);
+        let mut t = ToFutures::with_capacity(crate::adapters::FromTokio::new(a), 3);
+        let data: Vec<u8> = (0..100).collect();
+
+        block_on(async {
+            let mut received = vec![0; 100];
+            let (sent, read) = futures::join!(
+                async {
+                    futures::io::AsyncWriteExt::write_all(&mut t, &data).await?;
+                    futures::io::AsyncWriteExt::flush(&mut t).await
+                },
+                b.read_exact(&mut received),
+            );
+            sent.unwrap();
+            assert_eq!(read.unwrap(), 100);
+            assert_eq!(received, data);
+
+            let (written, received) = futures::join!(b.write_all(&data), async {
+                let mut received = Vec::new();
+                let mut buf = [0; 10];
+                while received.len() < 100 {
+                    let n = futures::io::AsyncReadExt::read(&mut t, &mut buf)
+                        .await
+                        .unwrap();
+                    assert!((1..=3).contains(&n));
+                    received.extend_from_slice(&buf[..n]);
+                }
+                received
+            });
+            written.unwrap();
+            assert_eq!(received, data);
+
+            // Interleaved reads and writes.
+            for i in 0..10u8 {
+                let mut buf = [0; 5];
+                futures::io::AsyncWriteExt::write_all(&mut t, &[i; 4])
+                    .await
+                    .unwrap();
+                b.read_exact(&mut buf[..4]).await.unwrap();
+                assert_eq!(buf[..4], [i; 4]);
+                b.write_all(&[!i; 5]).await.unwrap();
+                futures::io::AsyncReadExt::read_exact(&mut t, &mut buf[..5])
+                    .await
+                    .unwrap();
+                assert_eq!(buf[..5], [!i; 5]);
+            }
+
+            drop(b);
+            let n = futures::io::AsyncReadExt::read(&mut t, &mut [0; 4])
+                .await
+                .unwrap();
+            assert_eq!(n, 0);
+        });
+    }
+
+    #[test]
+    fn seek() {
+        use crate::SeekFrom::{Current, End};
+
+        let mock = Mock::new([
+            Transaction::Seek(End(-4), 6),
+            Transaction::read(b"wxyz"),
+            Transaction::Seek(Current(-4), 6),
+            Transaction::read(b"wxyz"),
+            Transaction::Seek(Current(-2), 8),
+        ]);
+        let pending = Fault::NONE.with_pending(1);
+        let faults = [Fault::NONE, pending, Fault::NONE, pending];
+        let mut t = ToFutures::new(Chaotic::new(mock, faults.into_iter()));
+        block_on(async {
+            assert_eq!(t.seek(futures::io::SeekFrom::End(-4)).await.unwrap(), 6);
+
+            // A read dropped while pending completes when seeking, and its data is discarded.
+            let mut buf = [0; 4];
+            assert!(t.read(&mut buf).now_or_never().is_none());
+            let pos = t.seek(futures::io::SeekFrom::Current(0)).await.unwrap();
+            assert_eq!(pos, 6);
+
+            // Same with a read into an empty buffer completing it.
+            assert!(t.read(&mut buf).now_or_never().is_none());
+            assert_eq!(t.read(&mut buf[..0]).await.unwrap(), 0);
+            assert_eq!(t.read(&mut buf[..2]).await.unwrap(), 2);
+            assert_eq!(&buf[..2], b"wx");
+            let pos = t.seek(futures::io::SeekFrom::Current(0)).await.unwrap();
+            assert_eq!(pos, 8);
+        });
+        t.into_inner().into_inner().done();
+    }
+}
@@ -0,0 +1,342 @@
+use core::marker::PhantomData;
+use core::pin::Pin;
+
+use futures::future::poll_fn;
+use futures::stream::{self, Stream, TryStream, TryStreamExt};
+use futures::Sink;
+
+/// Adapter from a `futures` [`Stream`] of byte chunks.
+///
+/// The stream yields `Result<B, E>`, where `B` is a chunk of bytes such as a `Vec<u8>` or a
+/// websocket message payload. Reads drain one chunk at a time, so message boundaries are lost.
+/// An error from the stream is returned by the read that reaches it, and the stream ending is EOF.
+///
+/// The stream must be `Unpin`; use `Box::pin` if it isn't. Its error type must implement
+/// [`Error`](crate::Error); use [`TryStreamExt::map_err`] to convert it if it doesn't.
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+pub struct StreamReader<S: TryStream> {
+    inner: S,
+    chunk: Option<S::Ok>,
+    pos: usize,
+    done: bool,
+}
+
+impl<S: TryStream> StreamReader<S> {
+    /// Create a new adapter.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            chunk: None,
+            pos: 0,
+            done: false,
+        }
+    }
+
+    /// Consume the adapter, returning the inner stream.
+    ///
+    /// Any data left in the current chunk is lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Borrow the inner stream.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner stream.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S: TryStream> crate::Io for StreamReader<S>
+where
+    S::Error: crate::Error,
+{
+    type Error = S::Error;
+}
+
+impl<S> crate::asynch::Read for StreamReader<S>
+where
+    S: TryStream + Unpin,
+    S::Ok: AsRef<[u8]>,
+    S::Error: crate::Error,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        use crate::asynch::BufRead;
+
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let avail = self.fill_buf().await?;
+        let n = core::cmp::min(avail.len(), buf.len());
+        buf[..n].copy_from_slice(&avail[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<S> crate::asynch::BufRead for StreamReader<S>
+where
+    S: TryStream + Unpin,
+    S::Ok: AsRef<[u8]>,
+    S::Error: crate::Error,
+{
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        while !self.done && self.pos == self.chunk.as_ref().map_or(0, |c| c.as_ref().len()) {
+            self.chunk = None;
+            self.pos = 0;
+            match self.inner.try_next().await? {
+                Some(chunk) => self.chunk = Some(chunk),
+                None => self.done = true,
+            }
+        }
+        Ok(match &self.chunk {
+            Some(chunk) => &chunk.as_ref()[self.pos..],
+            None => &[],
+        })
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+/// Adapter from a `futures` [`Sink`] of byte chunks.
+///
+/// Each write sends all of its data to the sink as one chunk, converted from a `Vec<u8>` with
+/// `B::from`, so a `write_all` sends a single message. Flushing flushes the sink.
+///
+/// The sink must be `Unpin`; use `Box::pin` if it isn't. Its error type must implement
+/// [`Error`](crate::Error); use `SinkExt::sink_map_err` to convert it if it doesn't.
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+pub struct SinkWriter<S, B = Vec<u8>> {
+    inner: S,
+    _chunk: PhantomData<fn(B)>,
+}
+
+impl<S, B> SinkWriter<S, B> {
+    /// Create a new adapter.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            _chunk: PhantomData,
+        }
+    }
+
+    /// Consume the adapter, returning the inner sink.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Borrow the inner sink.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner sink.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S: Sink<B> + Unpin, B> SinkWriter<S, B> {
+    /// Flush and close the sink.
+    pub async fn close(&mut self) -> Result<(), S::Error> {
+        poll_fn(|cx| Pin::new(&mut self.inner).poll_close(cx)).await
+    }
+}
+
+impl<S: Sink<B>, B> crate::Io for SinkWriter<S, B>
+where
+    S::Error: crate::Error,
+{
+    type Error = S::Error;
+}
+
+impl<S, B> crate::asynch::Write for SinkWriter<S, B>
+where
+    S: Sink<B> + Unpin,
+    S::Error: crate::Error,
+    B: From<Vec<u8>>,
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        poll_fn(|cx| Pin::new(&mut self.inner).poll_ready(cx)).await?;
+        Pin::new(&mut self.inner).start_send(B::from(buf.to_vec()))?;
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        poll_fn(|cx| Pin::new(&mut self.inner).poll_flush(cx)).await
+    }
+}
+
+/// Convert a reader into a `futures` [`Stream`] of byte chunks.
+///
+/// Each item is the data returned by one read of up to `buf_size` bytes. The stream ends at EOF,
+/// or after yielding an error.
+///
+/// # Panics
+///
+/// Panics if `buf_size` is 0.
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+pub fn into_chunk_stream<R: crate::asynch::Read>(
+    reader: R,
+    buf_size: usize,
+) -> impl Stream<Item = Result<Vec<u8>, R::Error>> {
+    assert!(buf_size > 0, "buffer size must be non-zero");
+    stream::unfold(Some(reader), move |reader| async move {
+        let mut reader = reader?;
+        let mut buf = vec![0; buf_size];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(buf), Some(reader)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::{block_on, Spin};
+    use crate::asynch::{BufRead, Read, Write};
+    use crate::chaos::{Chaotic, ChaoticError, Fault};
+    use crate::ErrorKind;
+    use futures::channel::mpsc;
+    use futures::{SinkExt, StreamExt};
+
+    #[test]
+    fn stream_reader() {
+        let chunks = vec![
+            Ok(b"ab".to_vec()),
+            Ok(vec![]),
+            Ok(b"cde".to_vec()),
+            Err(ErrorKind::Other),
+            Ok(b"f".to_vec()),
+        ];
+        let mut r = StreamReader::new(stream::iter(chunks));
+        let mut buf = [0; 4];
+        assert_eq!(block_on(r.read(&mut buf), &mut Spin), Ok(2));
+        assert_eq!(&buf[..2], b"ab");
+        // Empty chunks are skipped, and reads don't span chunks.
+        assert_eq!(block_on(r.read(&mut buf[..1]), &mut Spin), Ok(1));
+        assert_eq!(block_on(r.fill_buf(), &mut Spin), Ok(&b"de"[..]));
+        r.consume(2);
+        assert_eq!(block_on(r.read(&mut buf), &mut Spin), Err(ErrorKind::Other));
+        assert_eq!(block_on(r.read(&mut buf), &mut Spin), Ok(1));
+        assert_eq!(block_on(r.read(&mut buf), &mut Spin), Ok(0));
+        assert_eq!(block_on(r.fill_buf(), &mut Spin), Ok(&b""[..]));
+    }
+
+    #[test]
+    fn stream_reader_waits() {
+        let (tx, rx) = mpsc::unbounded::<Result<Vec<u8>, ErrorKind>>();
+        let mut r = StreamReader::new(rx);
+        let mut tx = Some(tx);
+        let mut polls = 0;
+        let mut buf = [0; 4];
+        // An empty read doesn't wait for data.
+        let read = r.read(&mut []);
+        assert_eq!(block_on(read, &mut || polls += 1), Ok(0));
+        assert_eq!(polls, 0);
+        let read = r.read(&mut buf);
+        assert_eq!(
+            block_on(read, &mut || {
+                polls += 1;
+                tx.as_ref()
+                    .unwrap()
+                    .unbounded_send(Ok(b"xyz".to_vec()))
+                    .unwrap();
+            }),
+            Ok(3)
+        );
+        assert_eq!(polls, 1);
+
+        // The stream ending is EOF, and stays EOF.
+        let read = r.read(&mut buf);
+        assert_eq!(block_on(read, &mut || drop(tx.take())), Ok(0));
+        assert_eq!(block_on(r.read(&mut buf), &mut Spin), Ok(0));
+    }
+
+    #[test]
+    fn sink_writer() {
+        let mut w = SinkWriter::new(Vec::<Vec<u8>>::new());
+        assert_eq!(block_on(w.write(b"abc"), &mut Spin), Ok(3));
+        assert_eq!(block_on(w.write(b""), &mut Spin), Ok(0));
+        assert_eq!(block_on(w.write_all(b"de"), &mut Spin), Ok(()));
+        assert_eq!(block_on(w.flush(), &mut Spin), Ok(()));
+        assert_eq!(block_on(w.close(), &mut Spin), Ok(()));
+        assert_eq!(w.into_inner(), [b"abc".to_vec(), b"de".to_vec()]);
+    }
+
+    #[test]
+    fn sink_writer_waits() {
+        // A zero-capacity channel takes one message per sender, then waits for the receiver.
+        let (tx, mut rx) = mpsc::channel::<Vec<u8>>(0);
+        let mut w = SinkWriter::new(tx.sink_map_err(|_| ErrorKind::Other));
+        let mut received = Vec::new();
+        let mut polls = 0;
+        for chunk in [&b"ab"[..], b"cd", b"e"] {
+            let write = w.write(chunk);
+            let res = block_on(write, &mut || {
+                polls += 1;
+                received.push(rx.try_recv().unwrap());
+            });
+            assert_eq!(res, Ok(chunk.len()));
+        }
+        assert_eq!(polls, 2);
+        assert_eq!(received, [b"ab".to_vec(), b"cd".to_vec()]);
+
+        drop(rx);
+        let res = block_on(w.write(b"f"), &mut Spin);
+        assert_eq!(res, Err(ErrorKind::Other));
+    }
+
+    #[test]
+    fn chunk_stream() {
+        let s = into_chunk_stream(&b"abcde"[..], 2);
+        assert_eq!(
+            block_on(s.collect::<Vec<_>>(), &mut Spin),
+            [Ok(b"ab".to_vec()), Ok(b"cd".to_vec()), Ok(b"e".to_vec())]
+        );
+
+        // The stream ends after an error.
+        let faults = [Fault::limit(1), Fault::error(ErrorKind::Other)];
+        let r = Chaotic::new(&b"abc"[..], faults.into_iter());
+        let s = into_chunk_stream(r, 2);
+        assert_eq!(
+            block_on(s.collect::<Vec<_>>(), &mut Spin),
+            [
+                Ok(b"a".to_vec()),
+                Err(ChaoticError::Injected(ErrorKind::Other))
+            ]
+        );
+
+        // Chunks read from a stream and written back out round-trip.
+        let mut w = SinkWriter::new(Vec::<Vec<u8>>::new());
+        let mut r = StreamReader::new(Box::pin(into_chunk_stream(&b"hello"[..], 3)));
+        let mut buf = [0; 8];
+        loop {
+            let n = unwrap!(block_on(r.read(&mut buf), &mut Spin));
+            if n == 0 {
+                break;
+            }
+            unwrap!(block_on(w.write(&buf[..n]), &mut Spin));
+        }
+        assert_eq!(w.into_inner(), [b"hel".to_vec(), b"lo".to_vec()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer size must be non-zero")]
+    fn chunk_stream_zero_size() {
+        let _ = into_chunk_stream(&b""[..], 0);
+    }
+}
@@ -0,0 +1,193 @@
+/// Reader over an iterator of bytes.
+///
+/// Reads take bytes from the iterator until the buffer is full or the iterator ends, so reading
+/// from an infinite iterator like [`core::iter::repeat`] always fills the buffer. Reads return
+/// `Ok(0)` once the iterator has ended.
+#[derive(Clone, Debug)]
+pub struct IterReader<I> {
+    inner: I,
+}
+
+impl<I: Iterator<Item = u8>> IterReader<I> {
+    /// Create a new reader.
+    pub fn new(inner: I) -> Self {
+        Self { inner }
+    }
+
+    /// Consume the reader, returning the inner iterator.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    /// Borrow the inner iterator.
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner iterator.
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) -> usize {
+        let mut n = 0;
+        for (slot, byte) in buf.iter_mut().zip(&mut self.inner) {
+            *slot = byte;
+            n += 1;
+        }
+        n
+    }
+}
+
+impl<I> crate::Io for IterReader<I> {
+    type Error = core::convert::Infallible;
+}
+
+impl<I: Iterator<Item = u8>> crate::blocking::Read for IterReader<I> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(self.fill(buf))
+    }
+}
+
+impl<I: Iterator<Item = u8>> crate::blocking::ReadReady for IterReader<I> {
+    #[inline]
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<I: Iterator<Item = u8>> crate::asynch::Read for IterReader<I> {
+    #[inline]
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(self.fill(buf))
+    }
+}
+
+/// Writer into a collection implementing [`Extend<u8>`].
+///
+/// Writes extend the collection with all of their data, so they never return short writes.
+#[derive(Clone, Debug)]
+pub struct ExtendWriter<E> {
+    inner: E,
+}
+
+impl<E: Extend<u8>> ExtendWriter<E> {
+    /// Create a new writer.
+    pub fn new(inner: E) -> Self {
+        Self { inner }
+    }
+
+    /// Consume the writer, returning the inner collection.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// Borrow the inner collection.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner collection.
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.inner
+    }
+}
+
+impl<E> crate::Io for ExtendWriter<E> {
+    type Error = core::convert::Infallible;
+}
+
+impl<E: Extend<u8>> crate::blocking::Write for ExtendWriter<E> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.extend(buf.iter().copied());
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<E: Extend<u8>> crate::blocking::WriteReady for ExtendWriter<E> {
+    #[inline]
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<E: Extend<u8>> crate::asynch::Write for ExtendWriter<E> {
+    #[inline]
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.inner.extend(buf.iter().copied());
+        Ok(buf.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::Read;
+
+    #[test]
+    fn iter_reader() {
+        let mut r = IterReader::new(b"abcde".iter().copied());
+        let mut buf = [0; 3];
+        assert_eq!(r.read(&mut buf), Ok(3));
+        assert_eq!(&buf, b"abc");
+        assert_eq!(r.read(&mut buf), Ok(2));
+        assert_eq!(&buf[..2], b"de");
+        assert_eq!(r.read(&mut buf), Ok(0));
+        assert_eq!(r.read(&mut buf), Ok(0));
+        assert_eq!(r.read(&mut []), Ok(0));
+
+        // An infinite iterator always fills the buffer, and an empty read consumes nothing.
+        let mut r = IterReader::new((0..=u8::MAX).cycle());
+        assert_eq!(r.read(&mut []), Ok(0));
+        let mut buf = [0; 300];
+        assert_eq!(r.read(&mut buf), Ok(300));
+        assert_eq!((buf[0], buf[255], buf[299]), (0, 255, 43));
+        assert_eq!(r.inner_mut().next(), Some(44));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extend_writer() {
+        use crate::blocking::Write;
+
+        let mut w = ExtendWriter::new(alloc::vec::Vec::new());
+        assert_eq!(w.write(b"abc"), Ok(3));
+        assert_eq!(w.write(b""), Ok(0));
+        assert_eq!(w.write_all(b"de"), Ok(()));
+        assert_eq!(w.flush(), Ok(()));
+        assert_eq!(w.into_inner(), b"abcde");
+    }
+
+    #[cfg(all(feature = "async", feature = "alloc"))]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+
+        let mut r = IterReader::new(core::iter::repeat(7).take(5));
+        let mut w = ExtendWriter::new(alloc::collections::VecDeque::new());
+        let mut buf = [0; 4];
+        loop {
+            let n = unwrap!(block_on(
+                crate::asynch::Read::read(&mut r, &mut buf),
+                &mut Spin
+            ));
+            if n == 0 {
+                break;
+            }
+            let write = crate::asynch::Write::write_all(&mut w, &buf[..n]);
+            unwrap!(block_on(write, &mut Spin));
+        }
+        assert_eq!(w.into_inner(), [7; 5]);
+    }
+}
@@ -7,6 +7,48 @@
 //! adapter implements the right traits based on what the inner type implements.
 //! This allows adapting a `Read+Write`, for example.
 
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+mod block_on;
+#[cfg(feature = "async")]
+pub use block_on::*;
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+mod from_blocking;
+#[cfg(feature = "async")]
+pub use from_blocking::*;
+
+mod fmt_write;
+pub use fmt_write::*;
+
+mod iter;
+pub use iter::*;
+
+#[cfg(feature = "embedded-hal-nb")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded-hal-nb")))]
+mod nb_serial;
+#[cfg(feature = "embedded-hal-nb")]
+pub use nb_serial::*;
+
+#[cfg(feature = "embedded-hal-02")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded-hal-02")))]
+mod nb_serial_02;
+#[cfg(feature = "embedded-hal-02")]
+pub use nb_serial_02::*;
+
+#[cfg(feature = "embedded-storage")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded-storage")))]
+mod storage;
+#[cfg(feature = "embedded-storage")]
+pub use storage::*;
+
+#[cfg(feature = "ufmt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ufmt")))]
+mod ufmt;
+#[cfg(feature = "ufmt")]
+pub use crate::adapters::ufmt::*;
+
 #[cfg(feature = "std")]
 #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 mod std_io;
@@ -19,15 +61,32 @@ mod futures_io;
 #[cfg(feature = "futures")]
 pub use futures_io::*;
 
+#[cfg(feature = "futures")]
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+mod futures_stream;
+#[cfg(feature = "futures")]
+pub use futures_stream::*;
+
 #[cfg(feature = "tokio")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
 mod tokio;
 #[cfg(feature = "tokio")]
 pub use crate::adapters::tokio::*;
 
+#[cfg(any(feature = "futures", feature = "tokio"))]
+mod driver;
+
 #[cfg(feature = "std")]
-fn to_io_error<T: core::fmt::Debug>(err: T) -> std::io::Error {
-    let kind = std::io::ErrorKind::Other;
+fn to_io_error<T: crate::Error>(err: T) -> std::io::Error {
+    let kind = match err.kind() {
+        crate::ErrorKind::TimedOut => std::io::ErrorKind::TimedOut,
+        crate::ErrorKind::InvalidData => std::io::ErrorKind::InvalidData,
+        crate::ErrorKind::WriteZero => std::io::ErrorKind::WriteZero,
+        crate::ErrorKind::UnexpectedEof => std::io::ErrorKind::UnexpectedEof,
+        crate::ErrorKind::InvalidInput => std::io::ErrorKind::InvalidInput,
+        crate::ErrorKind::OutOfMemory => std::io::ErrorKind::OutOfMemory,
+        _ => std::io::ErrorKind::Other,
+    };
     std::io::Error::new(kind, format!("{:?}", err))
 }
 
@@ -35,6 +94,14 @@ fn to_io_error<T: core::fmt::Debug>(err: T) -> std::io::Error {
 #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 impl crate::Error for std::io::Error {
     fn kind(&self) -> crate::ErrorKind {
-        crate::ErrorKind::Other
+        match std::io::Error::kind(self) {
+            std::io::ErrorKind::TimedOut => crate::ErrorKind::TimedOut,
+            std::io::ErrorKind::InvalidData => crate::ErrorKind::InvalidData,
+            std::io::ErrorKind::WriteZero => crate::ErrorKind::WriteZero,
+            std::io::ErrorKind::UnexpectedEof => crate::ErrorKind::UnexpectedEof,
+            std::io::ErrorKind::InvalidInput => crate::ErrorKind::InvalidInput,
+            std::io::ErrorKind::OutOfMemory => crate::ErrorKind::OutOfMemory,
+            _ => crate::ErrorKind::Other,
+        }
     }
 }
@@ -0,0 +1,339 @@
+use core::fmt;
+
+use embedded_hal_nb::nb;
+use embedded_hal_nb::serial;
+
+/// Error returned by [`FromNbSerial`], wrapping the driver error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SerialError<E>(pub E);
+
+impl<E: serial::Error> crate::Error for SerialError<E> {
+    fn kind(&self) -> crate::ErrorKind {
+        match self.0.kind() {
+            serial::ErrorKind::FrameFormat
+            | serial::ErrorKind::Parity
+            | serial::ErrorKind::Noise => crate::ErrorKind::InvalidData,
+            _ => crate::ErrorKind::Other,
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for SerialError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for SerialError<E> {}
+
+/// Adapter from `embedded-hal-nb` serial traits.
+///
+/// Blocking operations spin while the driver returns [`WouldBlock`](nb::Error::WouldBlock).
+/// Reads block until at least one byte is received, then return what's available without
+/// blocking further, and writes likewise.
+///
+/// Since the serial traits have no way to query readiness, [`ReadReady`] reads a byte ahead and
+/// keeps it for the next read. [`WriteReady`] reports whether the transmitter is idle, according
+/// to the driver's `flush`, so it may return `false` while there's room for more data.
+///
+/// [`ReadReady`]: crate::blocking::ReadReady
+/// [`WriteReady`]: crate::blocking::WriteReady
+pub struct FromNbSerial<S: serial::ErrorType> {
+    inner: S,
+    /// Byte read ahead by `read_ready`.
+    peeked: Option<u8>,
+    /// Error that ended a read after some bytes were received, returned by the next read.
+    read_error: Option<S::Error>,
+    /// Likewise for writes.
+    write_error: Option<S::Error>,
+}
+
+impl<S: serial::ErrorType> FromNbSerial<S> {
+    /// Create a new adapter.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            peeked: None,
+            read_error: None,
+            write_error: None,
+        }
+    }
+
+    /// Consume the adapter, returning the inner object.
+    ///
+    /// A byte read ahead by [`read_ready`](crate::blocking::ReadReady::read_ready) is lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S: serial::ErrorType> crate::Io for FromNbSerial<S> {
+    type Error = SerialError<S::Error>;
+}
+
+impl<S: serial::Read> crate::blocking::Read for FromNbSerial<S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(e) = self.read_error.take() {
+            return Err(SerialError(e));
+        }
+
+        buf[0] = match self.peeked.take() {
+            Some(byte) => byte,
+            None => nb::block!(self.inner.read()).map_err(SerialError)?,
+        };
+        let mut n = 1;
+        while n < buf.len() {
+            match self.inner.read() {
+                Ok(byte) => buf[n] = byte,
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => {
+                    self.read_error = Some(e);
+                    break;
+                }
+            }
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+impl<S: serial::Read> crate::blocking::ReadReady for FromNbSerial<S> {
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        if self.peeked.is_some() || self.read_error.is_some() {
+            return Ok(true);
+        }
+        match self.inner.read() {
+            Ok(byte) => {
+                self.peeked = Some(byte);
+                Ok(true)
+            }
+            Err(nb::Error::WouldBlock) => Ok(false),
+            Err(nb::Error::Other(e)) => Err(SerialError(e)),
+        }
+    }
+}
+
+impl<S: serial::Write> crate::blocking::Write for FromNbSerial<S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(e) = self.write_error.take() {
+            return Err(SerialError(e));
+        }
+
+        nb::block!(self.inner.write(buf[0])).map_err(SerialError)?;
+        let mut n = 1;
+        while n < buf.len() {
+            match self.inner.write(buf[n]) {
+                Ok(()) => {}
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => {
+                    self.write_error = Some(e);
+                    break;
+                }
+            }
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if let Some(e) = self.write_error.take() {
+            return Err(SerialError(e));
+        }
+        nb::block!(self.inner.flush()).map_err(SerialError)
+    }
+}
+
+impl<S: serial::Write> crate::blocking::WriteReady for FromNbSerial<S> {
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        if self.write_error.is_some() {
+            return Ok(true);
+        }
+        match self.inner.flush() {
+            Ok(()) => Ok(true),
+            Err(nb::Error::WouldBlock) => Ok(false),
+            Err(nb::Error::Other(e)) => Err(SerialError(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::{Read, ReadReady, Write, WriteReady};
+    use serial::ErrorKind::{Overrun, Parity};
+    use Step::*;
+
+    /// What the mock device does on each call.
+    #[derive(Clone, Copy)]
+    enum Step {
+        Byte(u8),
+        Ready,
+        Block,
+        Fail(serial::ErrorKind),
+    }
+
+    /// Serial device following a script, one step per call. Received bytes come from `Byte`
+    /// steps, and sent bytes are recorded when a write meets a `Ready` step.
+    struct Uart<'a> {
+        script: &'a [Step],
+        sent: [u8; 16],
+        sent_len: usize,
+    }
+
+    impl<'a> Uart<'a> {
+        fn new(script: &'a [Step]) -> Self {
+            Self {
+                script,
+                sent: [0; 16],
+                sent_len: 0,
+            }
+        }
+
+        fn step(&mut self) -> Step {
+            let (&step, rest) = self.script.split_first().expect("script exhausted");
+            self.script = rest;
+            step
+        }
+
+        fn sent(&self) -> &[u8] {
+            &self.sent[..self.sent_len]
+        }
+    }
+
+    impl serial::ErrorType for Uart<'_> {
+        type Error = serial::ErrorKind;
+    }
+
+    impl serial::Read for Uart<'_> {
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            match self.step() {
+                Step::Byte(byte) => Ok(byte),
+                Step::Block => Err(nb::Error::WouldBlock),
+                Step::Fail(e) => Err(nb::Error::Other(e)),
+                Step::Ready => panic!("unexpected read"),
+            }
+        }
+    }
+
+    impl serial::Write for Uart<'_> {
+        fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            match self.step() {
+                Step::Ready => {
+                    self.sent[self.sent_len] = word;
+                    self.sent_len += 1;
+                    Ok(())
+                }
+                Step::Block => Err(nb::Error::WouldBlock),
+                Step::Fail(e) => Err(nb::Error::Other(e)),
+                Step::Byte(_) => panic!("unexpected write"),
+            }
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            match self.step() {
+                Step::Ready => Ok(()),
+                Step::Block => Err(nb::Error::WouldBlock),
+                Step::Fail(e) => Err(nb::Error::Other(e)),
+                Step::Byte(_) => panic!("unexpected flush"),
+            }
+        }
+    }
+
+    #[test]
+    fn read() {
+        let mut s = FromNbSerial::new(Uart::new(&[
+            Block,
+            Block,
+            Byte(b'a'),
+            Byte(b'b'),
+            Block,
+            Block,
+            Byte(b'c'),
+            Block,
+        ]));
+        let mut buf = [0; 4];
+        // Blocks until a byte arrives, then returns what's available.
+        assert_eq!(s.read(&mut buf), Ok(2));
+        assert_eq!(&buf[..2], b"ab");
+        assert_eq!(s.read(&mut buf), Ok(1));
+        assert_eq!(buf[0], b'c');
+        assert_eq!(s.read(&mut []), Ok(0));
+        assert!(s.into_inner().script.is_empty());
+    }
+
+    #[test]
+    fn read_ready() {
+        let mut s = FromNbSerial::new(Uart::new(&[Block, Byte(b'a'), Byte(b'b'), Block]));
+        assert_eq!(s.read_ready(), Ok(false));
+        assert_eq!(s.read_ready(), Ok(true));
+        // The byte read ahead is kept.
+        assert_eq!(s.read_ready(), Ok(true));
+        let mut buf = [0; 4];
+        assert_eq!(s.read(&mut buf), Ok(2));
+        assert_eq!(&buf[..2], b"ab");
+    }
+
+    #[test]
+    fn read_errors() {
+        let mut s = FromNbSerial::new(Uart::new(&[Byte(b'a'), Fail(Parity), Block, Fail(Overrun)]));
+        let mut buf = [0; 4];
+        // An error after some bytes is returned by the next read.
+        assert_eq!(s.read(&mut buf), Ok(1));
+        assert_eq!(s.read_ready(), Ok(true));
+        let err = s.read(&mut buf).unwrap_err();
+        assert_eq!(err, SerialError(Parity));
+        assert_eq!(crate::Error::kind(&err), crate::ErrorKind::InvalidData);
+        let err = s.read(&mut buf).unwrap_err();
+        assert_eq!(crate::Error::kind(&err), crate::ErrorKind::Other);
+    }
+
+    #[test]
+    fn write() {
+        let mut s = FromNbSerial::new(Uart::new(&[
+            Block, Ready, Ready, Block, Block, Ready, Block, Block, Ready,
+        ]));
+        // Blocks until the first byte is accepted, then writes what it can.
+        assert_eq!(s.write(b"abc"), Ok(2));
+        assert_eq!(s.write(b"c"), Ok(1));
+        assert_eq!(s.flush(), Ok(()));
+        assert_eq!(s.inner().sent(), b"abc");
+        assert!(s.inner().script.is_empty());
+    }
+
+    #[test]
+    fn write_ready_and_errors() {
+        let mut s = FromNbSerial::new(Uart::new(&[
+            Block,
+            Ready,
+            Ready,
+            Fail(Overrun),
+            Fail(Parity),
+        ]));
+        assert_eq!(s.write_ready(), Ok(false));
+        assert_eq!(s.write_ready(), Ok(true));
+        // An error after some bytes is returned by the next write or flush.
+        assert_eq!(s.write(b"ab"), Ok(1));
+        assert_eq!(s.write_ready(), Ok(true));
+        assert_eq!(s.flush(), Err(SerialError(Overrun)));
+        assert_eq!(s.write(b"b"), Err(SerialError(Parity)));
+        assert_eq!(s.inner().sent(), b"a");
+    }
+}
@@ -0,0 +1,358 @@
+use core::fmt;
+
+use embedded_hal_02::serial;
+
+/// Error returned by [`FromNbSerial02`], wrapping the driver error.
+///
+/// embedded-hal 0.2 errors carry no kind, so this always has kind
+/// [`Other`](crate::ErrorKind::Other).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SerialError02<E>(pub E);
+
+impl<E: fmt::Debug> crate::Error for SerialError02<E> {
+    fn kind(&self) -> crate::ErrorKind {
+        crate::ErrorKind::Other
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for SerialError02<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for SerialError02<E> {}
+
+/// Adapter from embedded-hal 0.2 `serial::Read<u8>` and `serial::Write<u8>`.
+///
+/// This behaves like [`FromNbSerial`](crate::adapters::FromNbSerial), for drivers that
+/// haven't moved to `embedded-hal-nb` yet. Since the 0.2 traits each have their own error type,
+/// the adapter is generic over the error type `E`: reading requires `serial::Read<u8, Error =
+/// E>`, and writing `serial::Write<u8, Error = E>`. This is inferred from the operations used,
+/// and lets the adapter wrap a receiver or transmitter half on its own.
+pub struct FromNbSerial02<S, E> {
+    inner: S,
+    /// Byte read ahead by `read_ready`.
+    peeked: Option<u8>,
+    /// Error that ended a read after some bytes were received, returned by the next read.
+    read_error: Option<E>,
+    /// Likewise for writes.
+    write_error: Option<E>,
+}
+
+impl<S, E> FromNbSerial02<S, E> {
+    /// Create a new adapter.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            peeked: None,
+            read_error: None,
+            write_error: None,
+        }
+    }
+
+    /// Consume the adapter, returning the inner object.
+    ///
+    /// A byte read ahead by [`read_ready`](crate::blocking::ReadReady::read_ready) is lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S, E: fmt::Debug> crate::Io for FromNbSerial02<S, E> {
+    type Error = SerialError02<E>;
+}
+
+impl<S: serial::Read<u8, Error = E>, E: fmt::Debug> crate::blocking::Read for FromNbSerial02<S, E> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(e) = self.read_error.take() {
+            return Err(SerialError02(e));
+        }
+
+        buf[0] = match self.peeked.take() {
+            Some(byte) => byte,
+            None => nb::block!(self.inner.read()).map_err(SerialError02)?,
+        };
+        let mut n = 1;
+        while n < buf.len() {
+            match self.inner.read() {
+                Ok(byte) => buf[n] = byte,
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => {
+                    self.read_error = Some(e);
+                    break;
+                }
+            }
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+impl<S: serial::Read<u8, Error = E>, E: fmt::Debug> crate::blocking::ReadReady
+    for FromNbSerial02<S, E>
+{
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        if self.peeked.is_some() || self.read_error.is_some() {
+            return Ok(true);
+        }
+        match self.inner.read() {
+            Ok(byte) => {
+                self.peeked = Some(byte);
+                Ok(true)
+            }
+            Err(nb::Error::WouldBlock) => Ok(false),
+            Err(nb::Error::Other(e)) => Err(SerialError02(e)),
+        }
+    }
+}
+
+impl<S: serial::Write<u8, Error = E>, E: fmt::Debug> crate::blocking::Write
+    for FromNbSerial02<S, E>
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(e) = self.write_error.take() {
+            return Err(SerialError02(e));
+        }
+
+        nb::block!(self.inner.write(buf[0])).map_err(SerialError02)?;
+        let mut n = 1;
+        while n < buf.len() {
+            match self.inner.write(buf[n]) {
+                Ok(()) => {}
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => {
+                    self.write_error = Some(e);
+                    break;
+                }
+            }
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if let Some(e) = self.write_error.take() {
+            return Err(SerialError02(e));
+        }
+        nb::block!(self.inner.flush()).map_err(SerialError02)
+    }
+}
+
+impl<S: serial::Write<u8, Error = E>, E: fmt::Debug> crate::blocking::WriteReady
+    for FromNbSerial02<S, E>
+{
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        if self.write_error.is_some() {
+            return Ok(true);
+        }
+        match self.inner.flush() {
+            Ok(()) => Ok(true),
+            Err(nb::Error::WouldBlock) => Ok(false),
+            Err(nb::Error::Other(e)) => Err(SerialError02(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::{Read, ReadReady, Write, WriteReady};
+    use Step::*;
+
+    /// Driver error of the mock devices.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    enum Fault {
+        Overrun,
+        Framing,
+    }
+
+    /// What the mock device does on each call.
+    #[derive(Clone, Copy)]
+    enum Step {
+        Byte(u8),
+        Ready,
+        Block,
+        Fail(Fault),
+    }
+
+    /// Serial device following a script, one step per call. Received bytes come from `Byte`
+    /// steps, and sent bytes are recorded when a write meets a `Ready` step.
+    struct Uart<'a> {
+        script: &'a [Step],
+        sent: [u8; 16],
+        sent_len: usize,
+    }
+
+    impl<'a> Uart<'a> {
+        fn new(script: &'a [Step]) -> Self {
+            Self {
+                script,
+                sent: [0; 16],
+                sent_len: 0,
+            }
+        }
+
+        fn step(&mut self) -> Step {
+            let (&step, rest) = self.script.split_first().expect("script exhausted");
+            self.script = rest;
+            step
+        }
+
+        fn sent(&self) -> &[u8] {
+            &self.sent[..self.sent_len]
+        }
+    }
+
+    impl serial::Read<u8> for Uart<'_> {
+        type Error = Fault;
+
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            match self.step() {
+                Step::Byte(byte) => Ok(byte),
+                Step::Block => Err(nb::Error::WouldBlock),
+                Step::Fail(e) => Err(nb::Error::Other(e)),
+                Step::Ready => panic!("unexpected read"),
+            }
+        }
+    }
+
+    impl serial::Write<u8> for Uart<'_> {
+        type Error = Fault;
+
+        fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            match self.step() {
+                Step::Ready => {
+                    self.sent[self.sent_len] = word;
+                    self.sent_len += 1;
+                    Ok(())
+                }
+                Step::Block => Err(nb::Error::WouldBlock),
+                Step::Fail(e) => Err(nb::Error::Other(e)),
+                Step::Byte(_) => panic!("unexpected write"),
+            }
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            match self.step() {
+                Step::Ready => Ok(()),
+                Step::Block => Err(nb::Error::WouldBlock),
+                Step::Fail(e) => Err(nb::Error::Other(e)),
+                Step::Byte(_) => panic!("unexpected flush"),
+            }
+        }
+    }
+
+    /// Receiver half with an error type of its own, implementing only `serial::Read`.
+    struct Rx<'a>(&'a [u8]);
+
+    impl serial::Read<u8> for Rx<'_> {
+        type Error = core::convert::Infallible;
+
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            let (&byte, rest) = self.0.split_first().ok_or(nb::Error::WouldBlock)?;
+            self.0 = rest;
+            Ok(byte)
+        }
+    }
+
+    #[test]
+    fn read() {
+        let mut s = FromNbSerial02::new(Uart::new(&[
+            Block,
+            Block,
+            Byte(b'a'),
+            Byte(b'b'),
+            Block,
+            Byte(b'c'),
+            Block,
+        ]));
+        let mut buf = [0; 4];
+        // Blocks until a byte arrives, then returns what's available.
+        assert_eq!(s.read(&mut buf), Ok(2));
+        assert_eq!(&buf[..2], b"ab");
+        assert_eq!(s.read(&mut buf), Ok(1));
+        assert_eq!(buf[0], b'c');
+        assert_eq!(s.read(&mut []), Ok(0));
+        assert!(s.into_inner().script.is_empty());
+    }
+
+    #[test]
+    fn read_ready_and_errors() {
+        let mut s = FromNbSerial02::new(Uart::new(&[
+            Block,
+            Byte(b'a'),
+            Fail(Fault::Framing),
+            Fail(Fault::Overrun),
+        ]));
+        assert_eq!(s.read_ready(), Ok(false));
+        assert_eq!(s.read_ready(), Ok(true));
+        // The byte read ahead is returned first, and an error after it by the next read.
+        let mut buf = [0; 4];
+        assert_eq!(s.read(&mut buf), Ok(1));
+        assert_eq!(buf[0], b'a');
+        assert_eq!(s.read_ready(), Ok(true));
+        let err = s.read(&mut buf).unwrap_err();
+        assert_eq!(err, SerialError02(Fault::Framing));
+        assert_eq!(crate::Error::kind(&err), crate::ErrorKind::Other);
+        assert_eq!(s.read_ready(), Err(SerialError02(Fault::Overrun)));
+    }
+
+    #[test]
+    fn write() {
+        let mut s = FromNbSerial02::new(Uart::new(&[
+            Block,
+            Ready,
+            Ready,
+            Block,
+            Block,
+            Ready,
+            Block,
+            Ready,
+            Ready,
+            Fail(Fault::Overrun),
+            Fail(Fault::Framing),
+        ]));
+        // Blocks until the first byte is accepted, then writes what it can.
+        assert_eq!(s.write(b"abc"), Ok(2));
+        assert_eq!(s.write(b"c"), Ok(1));
+        assert_eq!(s.write_ready(), Ok(false));
+        assert_eq!(s.flush(), Ok(()));
+        // An error after some bytes is returned by the next write or flush.
+        assert_eq!(s.write(b"de"), Ok(1));
+        assert_eq!(s.write_ready(), Ok(true));
+        assert_eq!(s.flush(), Err(SerialError02(Fault::Overrun)));
+        assert_eq!(s.write(b"e"), Err(SerialError02(Fault::Framing)));
+        assert_eq!(s.inner().sent(), b"abcd");
+        assert!(s.inner().script.is_empty());
+    }
+
+    #[test]
+    fn receiver_half() {
+        let mut s = FromNbSerial02::new(Rx(b"xyz"));
+        let mut buf = [0; 2];
+        assert_eq!(s.read_exact(&mut buf), Ok(()));
+        assert_eq!(&buf, b"xy");
+        assert_eq!(s.read_ready(), Ok(true));
+        assert_eq!(s.read(&mut buf), Ok(1));
+        assert_eq!(s.read_ready(), Ok(false));
+    }
+}
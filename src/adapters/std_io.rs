@@ -68,6 +68,7 @@ impl<T: std::io::Seek + ?Sized> crate::blocking::Seek for FromStd<T> {
 
 /// Adapter to `std::io` traits.
 #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[derive(Clone)]
 pub struct ToStd<T: ?Sized> {
     inner: T,
 }
@@ -102,6 +103,16 @@ impl<T: crate::blocking::Read + ?Sized> std::io::Read for ToStd<T> {
     }
 }
 
+impl<T: crate::blocking::Read + crate::blocking::BufRead + ?Sized> std::io::BufRead for ToStd<T> {
+    fn fill_buf(&mut self) -> Result<&[u8], std::io::Error> {
+        self.inner.fill_buf().map_err(to_io_error)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
 impl<T: crate::blocking::Write + ?Sized> std::io::Write for ToStd<T> {
     fn write(&mut self, buf: &[u8]) -> Result<usize, std::io::Error> {
         self.inner.write(buf).map_err(to_io_error)
@@ -115,4 +126,101 @@ impl<T: crate::blocking::Seek + ?Sized> std::io::Seek for ToStd<T> {
     fn seek(&mut self, pos: std::io::SeekFrom) -> Result<u64, std::io::Error> {
         self.inner.seek(pos.into()).map_err(to_io_error)
     }
+
+    fn rewind(&mut self) -> Result<(), std::io::Error> {
+        self.inner.rewind().map_err(to_io_error)
+    }
+
+    fn stream_position(&mut self) -> Result<u64, std::io::Error> {
+        self.inner.stream_position().map_err(to_io_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Seek, Write};
+
+    use super::*;
+    use crate::ErrorKind;
+
+    /// Seekable object that only supports `rewind` and `stream_position`, to check they're
+    /// forwarded rather than implemented with `seek`.
+    struct Tape {
+        pos: u64,
+    }
+
+    impl crate::Io for Tape {
+        type Error = ErrorKind;
+    }
+
+    impl crate::blocking::Seek for Tape {
+        fn seek(&mut self, _: crate::SeekFrom) -> Result<u64, Self::Error> {
+            Err(ErrorKind::Other)
+        }
+
+        fn rewind(&mut self) -> Result<(), Self::Error> {
+            self.pos = 0;
+            Ok(())
+        }
+
+        fn stream_position(&mut self) -> Result<u64, Self::Error> {
+            Ok(self.pos)
+        }
+    }
+
+    #[test]
+    fn seek() {
+        let mut tape = ToStd::new(Tape { pos: 7 });
+        assert_eq!(tape.stream_position().unwrap(), 7);
+        tape.rewind().unwrap();
+        assert_eq!(tape.stream_position().unwrap(), 0);
+        let err = tape.seek(std::io::SeekFrom::Start(1)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut buf = [0; 8];
+        let mut w = ToStd::new(&mut buf[..]);
+        w.write_all(b"hello").unwrap();
+        let err = w.write_all(b"world").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+
+        let mut r = ToStd::new(&buf[..]);
+        let mut s = String::new();
+        r.read_to_string(&mut s).unwrap();
+        assert_eq!(s, "hellowor");
+    }
+
+    #[test]
+    fn buf_read() {
+        use std::io::BufRead;
+
+        let mut r = ToStd::new(&b"one\ntwo\r\nthree"[..]);
+        assert_eq!(r.fill_buf().unwrap(), b"one\ntwo\r\nthree");
+        r.consume(2);
+        let mut line = String::new();
+        assert_eq!(r.read_line(&mut line).unwrap(), 2);
+        assert_eq!(line, "e\n");
+        let lines: Vec<String> = r.lines().map(Result::unwrap).collect();
+        assert_eq!(lines, ["two", "three"]);
+
+        let mut r = ToStd::new(&b"a\nb"[..]);
+        line.clear();
+        assert_eq!(r.read_line(&mut line).unwrap(), 2);
+        assert_eq!(r.read_line(&mut line).unwrap(), 1);
+        assert_eq!(r.read_line(&mut line).unwrap(), 0);
+        assert_eq!(line, "a\nb");
+        assert_eq!(r.fill_buf().unwrap(), b"");
+
+        // Lines spanning several `fill_buf` calls.
+        let faults = std::iter::repeat(crate::chaos::Fault::limit(1));
+        let r = ToStd::new(crate::chaos::Chaotic::new(&b"ab\ncd\n"[..], faults));
+        let lines: Vec<String> = r.lines().map(Result::unwrap).collect();
+        assert_eq!(lines, ["ab", "cd"]);
+
+        let mut r = ToStd::new(&b"\xff\n"[..]);
+        let err = r.read_line(&mut String::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
 }
@@ -0,0 +1,712 @@
+use core::fmt;
+
+use core::ops::Range;
+
+use embedded_storage::nor_flash::{ErrorType, NorFlashError, NorFlashErrorKind};
+
+/// Error returned by [`StorageStream`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StorageError<E> {
+    /// Seek to a negative position, or past the 32-bit address space.
+    InvalidSeek,
+    /// Write that would erase blocks apart from the region already erased by the stream, with
+    /// [`erase_before_write`](StorageStream::erase_before_write).
+    OutsideErasedRegion,
+    /// Error returned by the flash.
+    Other(E),
+}
+
+impl<E> From<E> for StorageError<E> {
+    fn from(err: E) -> Self {
+        Self::Other(err)
+    }
+}
+
+impl<E: NorFlashError> crate::Error for StorageError<E> {
+    fn kind(&self) -> crate::ErrorKind {
+        match self {
+            Self::InvalidSeek | Self::OutsideErasedRegion => crate::ErrorKind::InvalidInput,
+            Self::Other(e) => match e.kind() {
+                NorFlashErrorKind::NotAligned | NorFlashErrorKind::OutOfBounds => {
+                    crate::ErrorKind::InvalidInput
+                }
+                _ => crate::ErrorKind::Other,
+            },
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for StorageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for StorageError<E> {}
+
+const fn align_down(x: u32, align: usize) -> u32 {
+    x - x % align as u32
+}
+
+const fn align_up(x: u32, align: usize) -> u32 {
+    align_down(x + align as u32 - 1, align)
+}
+
+/// Stream over a NOR flash, as a byte-addressed device covering its whole capacity.
+///
+/// Writes are collected in a buffer of `N` bytes, and written to the flash when it's full, when a
+/// write doesn't continue the buffered data, or on `flush`, padded with `0xFF` to the flash's
+/// write size. Writing at a position that isn't aligned to the write size pads the start of the
+/// buffer the same way. As programming a NOR flash only clears bits, this leaves the padded bytes
+/// unchanged, but flashes that don't allow writing a word twice require writes to be contiguous
+/// and only flushed at the end. Buffered data not flushed is lost when the stream is dropped.
+///
+/// By default, the flash must already be erased where it's written. With
+/// [`erase_before_write`](Self::erase_before_write), each erase block is erased when first
+/// written to, including any data in it before the position written.
+///
+/// Reading and seeking only need a [`ReadNorFlash`](embedded_storage::nor_flash::ReadNorFlash),
+/// so the stream can also be used over read-only flash. Reads are aligned to the flash's read
+/// size, going through a buffer of `N` bytes on the stack when needed, and see buffered data as
+/// if it had been written, but not the erase writing it out may need.
+///
+/// The stream isn't implemented over [`ReadStorage`](embedded_storage::ReadStorage), which is
+/// already byte-addressed: impls for it would overlap with the ones over `ReadNorFlash`, as a
+/// flash can implement both traits.
+///
+/// `N` must be a multiple of both the read and write sizes of the flash, which is checked at
+/// compile time. [`new`](Self::new) uses 64 bytes, and [`with_buffer`](Self::with_buffer) sets
+/// another size.
+pub struct StorageStream<S, const N: usize = 64> {
+    inner: S,
+    pos: u32,
+    buf: [u8; N],
+    /// Flash offset of the start of `buf`, aligned to the write size.
+    buf_start: u32,
+    /// Padding at the start of `buf`, before the data.
+    buf_head: usize,
+    /// Length of the padding and data in `buf`. Zero if nothing is buffered.
+    buf_len: usize,
+    erase: bool,
+    /// Region erased by the stream.
+    erased: Range<u32>,
+}
+
+impl<S: ErrorType> StorageStream<S> {
+    /// Create a new stream with the default buffer of 64 bytes, positioned at the start of the
+    /// flash.
+    pub fn new(inner: S) -> Self {
+        Self::with_buffer(inner, [0; 64])
+    }
+}
+
+impl<S: ErrorType, const N: usize> StorageStream<S, N> {
+    /// Create a new stream using `buf` as its buffer, positioned at the start of the flash.
+    ///
+    /// This sets the buffer size `N`. The contents of `buf` are ignored.
+    pub fn with_buffer(inner: S, buf: [u8; N]) -> Self {
+        Self {
+            inner,
+            pos: 0,
+            buf,
+            buf_start: 0,
+            buf_head: 0,
+            buf_len: 0,
+            erase: false,
+            erased: 0..0,
+        }
+    }
+
+    /// Erase each erase block before writing to it for the first time.
+    ///
+    /// The stream remembers a single contiguous region it erased, so writing sequentially
+    /// erases each block once, and writing again anywhere in the region erases nothing. Writing
+    /// apart from the region fails with [`StorageError::OutsideErasedRegion`], as the stream
+    /// would have to forget the earlier blocks were erased, and erase them again when written.
+    pub fn erase_before_write(mut self) -> Self {
+        self.erase = true;
+        self
+    }
+
+    /// Consume the stream, returning the inner object.
+    ///
+    /// Buffered data not flushed is lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Whether the buffered data must be written out before writing at the current position.
+    fn must_write_buffer(&self) -> bool {
+        self.buf_len == N || (self.buf_len != 0 && self.pos != self.buf_start + self.buf_len as u32)
+    }
+
+    /// Buffer as much of `data` as fits at the current position, returning how much was taken.
+    fn buffer(&mut self, data: &[u8], capacity: usize, write_size: usize) -> usize {
+        let left = (capacity as u32).saturating_sub(self.pos) as usize;
+        if left == 0 || data.is_empty() {
+            return 0;
+        }
+        if self.buf_len == 0 {
+            self.buf_start = align_down(self.pos, write_size);
+            self.buf_head = (self.pos - self.buf_start) as usize;
+            self.buf_len = self.buf_head;
+            self.buf[..self.buf_len].fill(0xFF);
+        }
+        let n = data.len().min(N - self.buf_len).min(left);
+        self.buf[self.buf_len..][..n].copy_from_slice(&data[..n]);
+        self.buf_len += n;
+        self.pos += n as u32;
+        n
+    }
+
+    /// Pad the buffer to the write size, returning the padded length.
+    fn pad_buffer(&mut self, write_size: usize) -> usize {
+        let len = align_up(self.buf_len as u32, write_size) as usize;
+        self.buf[self.buf_len..len].fill(0xFF);
+        len
+    }
+
+    /// Ranges to erase before writing to `range`: the blocks it covers below and above the
+    /// region erased so far.
+    fn erase_ranges(
+        &self,
+        range: Range<u32>,
+        erase_size: usize,
+        capacity: usize,
+    ) -> Result<[Range<u32>; 2], StorageError<S::Error>> {
+        let from = align_down(range.start, erase_size);
+        let to = align_up(range.end, erase_size).min(capacity as u32);
+        let erased = &self.erased;
+        if erased.is_empty() {
+            Ok([from..to, to..to])
+        } else if from <= erased.end && to >= erased.start {
+            Ok([
+                from.min(erased.start)..erased.start,
+                erased.end..to.max(erased.end),
+            ])
+        } else {
+            Err(StorageError::OutsideErasedRegion)
+        }
+    }
+
+    /// Add `range`, which is empty or adjacent to the erased region, to the region.
+    fn mark_erased(&mut self, range: Range<u32>) {
+        if self.erased.is_empty() {
+            self.erased = range;
+        } else {
+            self.erased = self.erased.start.min(range.start)..self.erased.end.max(range.end);
+        }
+    }
+
+    /// Flash range to read into the bounce buffer, to serve a read of `len` bytes at the
+    /// current position that isn't aligned to the read size.
+    fn bounce_range(&self, len: usize, read_size: usize) -> Range<u32> {
+        let start = align_down(self.pos, read_size);
+        let end = align_up(self.pos + len as u32, read_size).min(start + N as u32);
+        start..end
+    }
+
+    /// Copy data read into `bounce` for `range` to `buf`, returning the amount copied.
+    fn copy_bounce(&self, range: Range<u32>, bounce: &[u8], buf: &mut [u8]) -> usize {
+        let offset = (self.pos - range.start) as usize;
+        let n = buf.len().min(range.len() - offset);
+        buf[..n].copy_from_slice(&bounce[offset..][..n]);
+        n
+    }
+
+    /// Copy the buffered data over `buf`, just read at the current position, and advance past
+    /// it.
+    fn finish_read(&mut self, buf: &mut [u8]) -> usize {
+        let data_start = self.buf_start + self.buf_head as u32;
+        let data_end = self.buf_start + self.buf_len as u32;
+        let from = data_start.max(self.pos);
+        let to = data_end.min(self.pos + buf.len() as u32);
+        if from < to {
+            let data = &self.buf[(from - self.buf_start) as usize..(to - self.buf_start) as usize];
+            buf[(from - self.pos) as usize..(to - self.pos) as usize].copy_from_slice(data);
+        }
+        self.pos += buf.len() as u32;
+        buf.len()
+    }
+
+    fn seek_pos(
+        &self,
+        pos: crate::SeekFrom,
+        capacity: usize,
+    ) -> Result<u32, StorageError<S::Error>> {
+        let pos = match pos {
+            crate::SeekFrom::Start(n) => Some(n),
+            crate::SeekFrom::End(n) => (capacity as u64).checked_add_signed(n),
+            crate::SeekFrom::Current(n) => (self.pos as u64).checked_add_signed(n),
+        };
+        pos.and_then(|pos| pos.try_into().ok())
+            .ok_or(StorageError::InvalidSeek)
+    }
+}
+
+impl<S: ErrorType, const N: usize> crate::Io for StorageStream<S, N> {
+    type Error = StorageError<S::Error>;
+}
+
+impl<S: embedded_storage::nor_flash::ReadNorFlash, const N: usize> StorageStream<S, N> {
+    // These checks fail the build when referenced by the operations relying on them.
+    const READ_SIZE_CHECK: () = ::core::assert!(
+        N >= S::READ_SIZE && N % S::READ_SIZE == 0,
+        "the buffer size must be a multiple of the read size"
+    );
+}
+
+impl<S: embedded_storage::nor_flash::ReadNorFlash, const N: usize> crate::blocking::Read
+    for StorageStream<S, N>
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::READ_SIZE_CHECK;
+        let left = (self.inner.capacity() as u32).saturating_sub(self.pos) as usize;
+        let len = buf.len().min(left);
+        if len == 0 {
+            return Ok(0);
+        }
+        let n = if self.pos as usize % S::READ_SIZE == 0 && len >= S::READ_SIZE {
+            let n = len - len % S::READ_SIZE;
+            self.inner.read(self.pos, &mut buf[..n])?;
+            n
+        } else {
+            let mut bounce = [0; N];
+            let range = self.bounce_range(len, S::READ_SIZE);
+            self.inner.read(range.start, &mut bounce[..range.len()])?;
+            self.copy_bounce(range, &bounce, &mut buf[..len])
+        };
+        Ok(self.finish_read(&mut buf[..n]))
+    }
+}
+
+impl<S: embedded_storage::nor_flash::ReadNorFlash, const N: usize> crate::blocking::Seek
+    for StorageStream<S, N>
+{
+    fn seek(&mut self, pos: crate::SeekFrom) -> Result<u64, Self::Error> {
+        self.pos = self.seek_pos(pos, self.inner.capacity())?;
+        Ok(self.pos as u64)
+    }
+}
+
+impl<S: embedded_storage::nor_flash::NorFlash, const N: usize> StorageStream<S, N> {
+    const WRITE_SIZE_CHECK: () = ::core::assert!(
+        N >= S::WRITE_SIZE && N % S::WRITE_SIZE == 0,
+        "the buffer size must be a multiple of the write size"
+    );
+
+    /// Write out the buffered data.
+    fn write_buffer(&mut self) -> Result<(), StorageError<S::Error>> {
+        if self.buf_len == 0 {
+            return Ok(());
+        }
+        let len = self.pad_buffer(S::WRITE_SIZE);
+        let range = self.buf_start..self.buf_start + len as u32;
+        if self.erase {
+            for erase in self.erase_ranges(range.clone(), S::ERASE_SIZE, self.inner.capacity())? {
+                if !erase.is_empty() {
+                    self.inner.erase(erase.start, erase.end)?;
+                    self.mark_erased(erase);
+                }
+            }
+        }
+        self.inner.write(range.start, &self.buf[..len])?;
+        self.buf_len = 0;
+        Ok(())
+    }
+}
+
+impl<S: embedded_storage::nor_flash::NorFlash, const N: usize> crate::blocking::Write
+    for StorageStream<S, N>
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::WRITE_SIZE_CHECK;
+        if self.must_write_buffer() {
+            self.write_buffer()?;
+        }
+        Ok(self.buffer(buf, self.inner.capacity(), S::WRITE_SIZE))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.write_buffer()
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded-storage-async")))]
+impl<S: embedded_storage_async::nor_flash::ReadNorFlash, const N: usize> StorageStream<S, N> {
+    const READ_SIZE_CHECK_ASYNC: () = ::core::assert!(
+        N >= S::READ_SIZE && N % S::READ_SIZE == 0,
+        "the buffer size must be a multiple of the read size"
+    );
+}
+
+#[cfg(feature = "embedded-storage-async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded-storage-async")))]
+impl<S: embedded_storage_async::nor_flash::ReadNorFlash, const N: usize> crate::asynch::Read
+    for StorageStream<S, N>
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::READ_SIZE_CHECK_ASYNC;
+        let left = (self.inner.capacity() as u32).saturating_sub(self.pos) as usize;
+        let len = buf.len().min(left);
+        if len == 0 {
+            return Ok(0);
+        }
+        let n = if self.pos as usize % S::READ_SIZE == 0 && len >= S::READ_SIZE {
+            let n = len - len % S::READ_SIZE;
+            self.inner.read(self.pos, &mut buf[..n]).await?;
+            n
+        } else {
+            let mut bounce = [0; N];
+            let range = self.bounce_range(len, S::READ_SIZE);
+            self.inner
+                .read(range.start, &mut bounce[..range.len()])
+                .await?;
+            self.copy_bounce(range, &bounce, &mut buf[..len])
+        };
+        Ok(self.finish_read(&mut buf[..n]))
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded-storage-async")))]
+impl<S: embedded_storage_async::nor_flash::ReadNorFlash, const N: usize> crate::asynch::Seek
+    for StorageStream<S, N>
+{
+    async fn seek(&mut self, pos: crate::SeekFrom) -> Result<u64, Self::Error> {
+        self.pos = self.seek_pos(pos, self.inner.capacity())?;
+        Ok(self.pos as u64)
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded-storage-async")))]
+impl<S: embedded_storage_async::nor_flash::NorFlash, const N: usize> StorageStream<S, N> {
+    const WRITE_SIZE_CHECK_ASYNC: () = ::core::assert!(
+        N >= S::WRITE_SIZE && N % S::WRITE_SIZE == 0,
+        "the buffer size must be a multiple of the write size"
+    );
+
+    /// Write out the buffered data.
+    async fn write_buffer_async(&mut self) -> Result<(), StorageError<S::Error>> {
+        if self.buf_len == 0 {
+            return Ok(());
+        }
+        let len = self.pad_buffer(S::WRITE_SIZE);
+        let range = self.buf_start..self.buf_start + len as u32;
+        if self.erase {
+            for erase in self.erase_ranges(range.clone(), S::ERASE_SIZE, self.inner.capacity())? {
+                if !erase.is_empty() {
+                    self.inner.erase(erase.start, erase.end).await?;
+                    self.mark_erased(erase);
+                }
+            }
+        }
+        self.inner.write(range.start, &self.buf[..len]).await?;
+        self.buf_len = 0;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded-storage-async")))]
+impl<S: embedded_storage_async::nor_flash::NorFlash, const N: usize> crate::asynch::Write
+    for StorageStream<S, N>
+{
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::WRITE_SIZE_CHECK_ASYNC;
+        if self.must_write_buffer() {
+            self.write_buffer_async().await?;
+        }
+        Ok(self.buffer(buf, self.inner.capacity(), S::WRITE_SIZE))
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.write_buffer_async().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_storage::nor_flash::{self, NorFlash, ReadNorFlash};
+
+    use super::*;
+    use crate::blocking::{Read, Seek, Write};
+    use crate::SeekFrom;
+
+    const CAPACITY: usize = 64;
+
+    /// In-memory NOR flash, with 2-byte reads, 4-byte writes and 16-byte erase blocks. Writes
+    /// clear bits, like programming a real flash.
+    struct Flash {
+        data: [u8; CAPACITY],
+        erases: usize,
+    }
+
+    impl Flash {
+        fn new(fill: u8) -> Self {
+            Self {
+                data: [fill; CAPACITY],
+                erases: 0,
+            }
+        }
+    }
+
+    impl ErrorType for Flash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for Flash {
+        const READ_SIZE: usize = 2;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            nor_flash::check_read(self, offset, bytes.len())?;
+            bytes.copy_from_slice(&self.data[offset as usize..][..bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            CAPACITY
+        }
+    }
+
+    impl NorFlash for Flash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = 16;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            nor_flash::check_erase(self, from, to)?;
+            self.data[from as usize..to as usize].fill(0xFF);
+            self.erases += 1;
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            nor_flash::check_write(self, offset, bytes.len())?;
+            for (word, byte) in self.data[offset as usize..].iter_mut().zip(bytes) {
+                *word &= byte;
+            }
+            Ok(())
+        }
+    }
+
+    /// Read-only flash, with 4-byte reads.
+    struct Rom(&'static [u8]);
+
+    impl ErrorType for Rom {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for Rom {
+        const READ_SIZE: usize = 4;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            nor_flash::check_read(self, offset, bytes.len())?;
+            bytes.copy_from_slice(&self.0[offset as usize..][..bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn read_only() {
+        let mut s = StorageStream::with_buffer(Rom(b"0123456789abcdefghij"), [0; 8]);
+        let mut buf = [0; 16];
+        // Unaligned reads go through the bounce buffer.
+        assert_eq!(s.seek(SeekFrom::Start(3)), Ok(3));
+        assert_eq!(s.read(&mut buf[..2]), Ok(2));
+        assert_eq!(&buf[..2], b"34");
+        assert_eq!(s.read(&mut buf), Ok(7));
+        assert_eq!(&buf[..7], b"56789ab");
+        // Aligned reads go straight to the flash, in multiples of the read size.
+        assert_eq!(s.read(&mut buf[..7]), Ok(4));
+        assert_eq!(&buf[..4], b"cdef");
+        assert_eq!(s.read(&mut buf), Ok(4));
+        assert_eq!(&buf[..4], b"ghij");
+        assert_eq!(s.read(&mut buf), Ok(0));
+
+        assert_eq!(s.seek(SeekFrom::End(-1)), Ok(19));
+        assert_eq!(s.read(&mut buf), Ok(1));
+        assert_eq!(buf[0], b'j');
+        assert_eq!(
+            s.seek(SeekFrom::Current(-21)),
+            Err(StorageError::InvalidSeek)
+        );
+    }
+
+    #[test]
+    fn default_buffer() {
+        let mut s = StorageStream::new(Flash::new(0xFF));
+        // The 64-byte buffer holds the whole flash before it's written out.
+        s.write_all(&[0x5A; CAPACITY]).unwrap();
+        assert_eq!(s.inner().data, [0xFF; CAPACITY]);
+        s.flush().unwrap();
+        assert_eq!(s.inner().data, [0x5A; CAPACITY]);
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let mut s = StorageStream::with_buffer(Flash::new(0xFF), [0; 8]);
+        s.write_all(b"hello, world").unwrap();
+        // Reads see buffered data.
+        assert_eq!(s.seek(SeekFrom::Start(0)), Ok(0));
+        let mut buf = [0; 16];
+        s.read_exact(&mut buf[..12]).unwrap();
+        assert_eq!(&buf[..12], b"hello, world");
+        assert_eq!(&s.inner().data[..12], b"hello, w\xff\xff\xff\xff");
+
+        s.flush().unwrap();
+        assert_eq!(&s.inner().data[..13], b"hello, world\xff");
+        assert_eq!(s.inner().erases, 0);
+    }
+
+    #[test]
+    fn unaligned_write() {
+        let mut s = StorageStream::with_buffer(Flash::new(0xFF), [0; 8]);
+        s.seek(SeekFrom::Start(5)).unwrap();
+        s.write_all(b"abc").unwrap();
+        // Writing elsewhere writes out the buffered data first.
+        s.seek(SeekFrom::Start(13)).unwrap();
+        s.write_all(b"de").unwrap();
+        assert_eq!(
+            &s.inner().data[4..16],
+            b"\xffabc\xff\xff\xff\xff\xff\xff\xff\xff"
+        );
+        s.flush().unwrap();
+        assert_eq!(&s.inner().data[4..16], b"\xffabc\xff\xff\xff\xff\xffde\xff");
+
+        // The write stops at the end of the flash.
+        s.seek(SeekFrom::End(-2)).unwrap();
+        assert_eq!(s.write(b"xyz"), Ok(2));
+        assert_eq!(s.write(b"z"), Ok(0));
+        s.flush().unwrap();
+        assert_eq!(&s.inner().data[60..], b"\xff\xffxy");
+    }
+
+    #[test]
+    fn erase_before_write() {
+        let mut s = StorageStream::with_buffer(Flash::new(0), [0; 8]).erase_before_write();
+        let data: [u8; 40] = core::array::from_fn(|i| i as u8 + 1);
+        s.write_all(&data).unwrap();
+        s.flush().unwrap();
+        assert_eq!(s.inner().erases, 3);
+        assert_eq!(&s.inner().data[..40], &data);
+        // Rest of the last block erased.
+        assert_eq!(&s.inner().data[40..48], &[0xFF; 8]);
+        assert_eq!(&s.inner().data[48..], &[0; 16]);
+
+        // Rewriting in the region erased doesn't erase it again.
+        s.seek(SeekFrom::Start(4)).unwrap();
+        s.write_all(&data[4..8]).unwrap();
+        s.flush().unwrap();
+        assert_eq!(s.inner().erases, 3);
+        assert_eq!(&s.inner().data[..40], &data);
+
+        // Neither does seeking past it and back, or extending it.
+        s.seek(SeekFrom::Start(48)).unwrap();
+        s.write_all(b"end").unwrap();
+        s.seek(SeekFrom::Start(40)).unwrap();
+        s.write_all(b"mid").unwrap();
+        s.flush().unwrap();
+        assert_eq!(s.inner().erases, 4);
+        assert_eq!(&s.inner().data[..40], &data);
+        assert_eq!(&s.inner().data[40..51], b"mid\xff\xff\xff\xff\xffend");
+    }
+
+    #[test]
+    fn erase_outside_region() {
+        let mut s = StorageStream::with_buffer(Flash::new(0), [0; 8]).erase_before_write();
+        s.seek(SeekFrom::Start(20)).unwrap();
+        s.write_all(b"data").unwrap();
+        s.flush().unwrap();
+
+        // The block below is adjacent, so the region grows.
+        s.seek(SeekFrom::Start(14)).unwrap();
+        s.write_all(b"ab").unwrap();
+        s.flush().unwrap();
+        assert_eq!(s.inner().erases, 2);
+
+        // Writing apart from the region would lose track of it.
+        s.seek(SeekFrom::Start(48)).unwrap();
+        s.write_all(b"x").unwrap();
+        assert_eq!(s.flush(), Err(StorageError::OutsideErasedRegion));
+        assert_eq!(
+            crate::Error::kind(&StorageError::<NorFlashErrorKind>::OutsideErasedRegion),
+            crate::ErrorKind::InvalidInput
+        );
+        assert_eq!(s.inner().erases, 2);
+        assert_eq!(&s.inner().data[14..24], b"ab\xff\xff\xff\xffdata");
+    }
+
+    #[cfg(feature = "embedded-storage-async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+
+        impl embedded_storage_async::nor_flash::ReadNorFlash for Flash {
+            const READ_SIZE: usize = 2;
+
+            async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+                ReadNorFlash::read(self, offset, bytes)
+            }
+
+            fn capacity(&self) -> usize {
+                CAPACITY
+            }
+        }
+
+        impl embedded_storage_async::nor_flash::NorFlash for Flash {
+            const WRITE_SIZE: usize = 4;
+            const ERASE_SIZE: usize = 16;
+
+            async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+                NorFlash::erase(self, from, to)
+            }
+
+            async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+                NorFlash::write(self, offset, bytes)
+            }
+        }
+
+        let mut s = StorageStream::with_buffer(Flash::new(0), [0; 8]).erase_before_write();
+        let mut buf = [0; 8];
+        block_on(
+            async {
+                crate::asynch::Seek::seek(&mut s, SeekFrom::Start(3)).await?;
+                crate::asynch::Write::write_all(&mut s, b"async")
+                    .await
+                    .unwrap();
+                crate::asynch::Seek::seek(&mut s, SeekFrom::Start(1)).await?;
+                crate::asynch::Read::read_exact(&mut s, &mut buf)
+                    .await
+                    .unwrap();
+                crate::asynch::Write::flush(&mut s).await
+            },
+            &mut Spin,
+        )
+        .unwrap();
+        // The flash after the data isn't erased yet when it's read.
+        assert_eq!(&buf, b"\0\0async\0");
+        assert_eq!(&s.inner().data[..10], b"\xff\xff\xffasync\xff\xff");
+    }
+}
@@ -1,5 +1,8 @@
 use core::pin::Pin;
-use core::task::Poll;
+use core::task::{Context, Poll};
+
+use super::driver::{Driver, DEFAULT_CAPACITY};
+use super::to_io_error;
 
 /// Adapter from `tokio::io` traits.
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
@@ -52,6 +55,16 @@ impl<T: tokio::io::AsyncRead + Unpin + ?Sized> crate::asynch::Read for FromTokio
     }
 }
 
+impl<T: tokio::io::AsyncBufRead + Unpin + ?Sized> crate::asynch::BufRead for FromTokio<T> {
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        tokio::io::AsyncBufReadExt::fill_buf(&mut self.inner).await
+    }
+
+    fn consume(&mut self, amt: usize) {
+        Pin::new(&mut self.inner).consume(amt)
+    }
+}
+
 impl<T: tokio::io::AsyncWrite + Unpin + ?Sized> crate::asynch::Write for FromTokio<T> {
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
         poll_fn::poll_fn(|cx| Pin::new(&mut self.inner).poll_write(cx, buf)).await
@@ -72,11 +85,139 @@ impl<T: tokio::io::AsyncSeek + Unpin + ?Sized> crate::asynch::Seek for FromTokio
     }
 }
 
-// TODO: ToTokio.
-// It's a bit tricky because tokio::io is "stateless", while we're "stateful" (we
-// return futures that borrow Self and get polled for the duration of the operation.)
-// It can probably done by storing the futures in Self, with unsafe Pin hacks because
-// we're a self-referential struct
+/// Adapter to `tokio::io` traits.
+///
+/// This works the same way as `ToFutures` from the `futures` feature, and the same buffer
+/// semantics apply. [`poll_shutdown`](tokio::io::AsyncWrite::poll_shutdown) flushes the inner
+/// writer, since [`asynch::Write`](crate::asynch::Write) has no notion of shutting down.
+///
+/// A seek started with [`start_seek`](tokio::io::AsyncSeek::start_seek) runs when
+/// [`poll_complete`](tokio::io::AsyncSeek::poll_complete) is called. The position it returns
+/// is kept until the next read or write, so that calling `poll_complete` with no seek started,
+/// as tokio's `seek` does first, or seeking to `Current(0)`, returns it without seeking the
+/// inner object. Otherwise, these seek to the current position, to return it.
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub struct ToTokio<T: crate::Io> {
+    driver: Driver<T>,
+    /// Position of the seek started but not completed.
+    seek: Option<crate::SeekFrom>,
+    /// Position returned by the last seek, if nothing has moved it since.
+    pos: Option<u64>,
+}
+
+impl<T: crate::Io> ToTokio<T> {
+    /// Create a new adapter, with the default buffer capacity.
+    pub fn new(inner: T) -> Self {
+        Self::with_capacity(inner, DEFAULT_CAPACITY)
+    }
+
+    /// Create a new adapter, with read and write buffers of `capacity` bytes each.
+    ///
+    /// This limits the amount of data each read and write transfers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(inner: T, capacity: usize) -> Self {
+        Self {
+            driver: Driver::new(inner, capacity),
+            seek: None,
+            pos: None,
+        }
+    }
+
+    /// Consume the adapter, returning the inner object.
+    ///
+    /// Any in-flight operation is cancelled, and buffered read data is lost.
+    pub fn into_inner(self) -> T {
+        self.driver.into_inner()
+    }
+
+    /// Mutably borrow the inner object.
+    ///
+    /// Any in-flight operation is cancelled first, as it borrows the inner object. Buffered
+    /// read data and results of completed operations are kept.
+    pub fn inner_mut(&mut self) -> &mut T {
+        self.seek = None;
+        self.pos = None;
+        self.driver.inner_mut()
+    }
+}
+
+impl<T: crate::asynch::Read> tokio::io::AsyncRead for ToTokio<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let this = self.get_mut();
+        this.pos = None;
+        match this.driver.poll_read(cx, buf.initialize_unfilled()) {
+            Poll::Ready(Ok(n)) => {
+                buf.advance(n);
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(to_io_error(e))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T: crate::asynch::Write> tokio::io::AsyncWrite for ToTokio<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        let this = self.get_mut();
+        this.pos = None;
+        this.driver.poll_write(cx, buf).map_err(to_io_error)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        self.get_mut().driver.poll_flush(cx).map_err(to_io_error)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl<T: crate::asynch::Seek> tokio::io::AsyncSeek for ToTokio<T> {
+    fn start_seek(self: Pin<&mut Self>, pos: std::io::SeekFrom) -> Result<(), std::io::Error> {
+        let this = self.get_mut();
+        if this.seek.is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "other seek in progress",
+            ));
+        }
+        this.seek = Some(pos.into());
+        Ok(())
+    }
+
+    fn poll_complete(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<u64, std::io::Error>> {
+        let this = self.get_mut();
+        let pos = this.seek.unwrap_or(crate::SeekFrom::Current(0));
+        if let (crate::SeekFrom::Current(0), Some(cached)) = (pos, this.pos) {
+            this.seek = None;
+            return Poll::Ready(Ok(cached));
+        }
+        let res = match this.driver.poll_seek(cx, pos) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        this.seek = None;
+        this.pos = res.as_ref().ok().copied();
+        Poll::Ready(res.map_err(to_io_error))
+    }
+}
 
 mod poll_fn {
     use core::future::Future;
@@ -103,7 +244,130 @@ mod poll_fn {
         type Output = T;
 
         fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
-            (&mut self.f)(cx)
+            (self.f)(cx)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+    use tokio::io::{duplex, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::chaos::{Chaotic, Fault};
+    use crate::mock::{Mock, Transaction};
+
+    #[tokio::test]
+    async fn fill_buf() {
+        use crate::asynch::BufRead;
+
+        let (a, mut b) = duplex(8);
+        let mut r = FromTokio::new(tokio::io::BufReader::with_capacity(4, a));
+        b.write_all(b"abcdef").await.unwrap();
+        drop(b);
+        assert_eq!(r.fill_buf().await.unwrap(), b"abcd");
+        r.consume(3);
+        assert_eq!(r.fill_buf().await.unwrap(), b"d");
+        r.consume(1);
+        assert_eq!(r.fill_buf().await.unwrap(), b"ef");
+        r.consume(2);
+        assert_eq!(r.fill_buf().await.unwrap(), b"");
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        // Small buffers, so that transfers are partial.
+        let (a, mut b) = duplex(5);
+        let mut t = ToTokio::with_capacity(FromTokio::new(a), 3);
+        let data: Vec<u8> = (0..100).collect();
+
+        let mut received = vec![0; 100];
+        let (sent, read) = tokio::join!(
+            async {
+                t.write_all(&data).await?;
+                t.flush().await
+            },
+            b.read_exact(&mut received),
+        );
+        sent.unwrap();
+        assert_eq!(read.unwrap(), 100);
+        assert_eq!(received, data);
+
+        let (written, mut received) = tokio::join!(b.write_all(&data), async {
+            let mut received = Vec::new();
+            let mut buf = [0; 10];
+            while received.len() < 100 {
+                let n = t.read(&mut buf).await.unwrap();
+                assert!((1..=3).contains(&n));
+                received.extend_from_slice(&buf[..n]);
+            }
+            received
+        });
+        written.unwrap();
+        assert_eq!(received, data);
+
+        drop(b);
+        assert_eq!(t.read(&mut received).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn interleaved() {
+        let (a, mut b) = duplex(8);
+        let mut t = ToTokio::with_capacity(FromTokio::new(a), 4);
+        for i in 0..10u8 {
+            let mut buf = [0; 6];
+            t.write_all(&[i; 6]).await.unwrap();
+            b.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [i; 6]);
+            b.write_all(&[!i; 5]).await.unwrap();
+            t.read_exact(&mut buf[..5]).await.unwrap();
+            assert_eq!(buf[..5], [!i; 5]);
+        }
+    }
+
+    #[tokio::test]
+    async fn seek() {
+        use crate::SeekFrom::{Current, End, Start};
+
+        // Tokio's `seek` completes any previous seek first. With no position known yet, that
+        // seeks to the current position; afterwards, it returns the position of the last seek.
+        let mock = Mock::new([
+            Transaction::Seek(Current(0), 0),
+            Transaction::Seek(End(-4), 6),
+            Transaction::read(b"wxyz"),
+            Transaction::Seek(Current(-4), 6),
+            Transaction::read(b"wxyz"),
+            Transaction::Seek(Current(-2), 8),
+            Transaction::Seek(Start(1), 1),
+            Transaction::write(b"ab"),
+            Transaction::Flush,
+            Transaction::Seek(Current(0), 3),
+        ]);
+        let pending = Fault::NONE.with_pending(1);
+        let faults = [Fault::NONE, Fault::NONE, pending, Fault::NONE, pending];
+        let mut t = ToTokio::new(Chaotic::new(mock, faults.into_iter()));
+        assert_eq!(t.seek(std::io::SeekFrom::End(-4)).await.unwrap(), 6);
+
+        // A read dropped while pending completes when seeking, and its data is discarded.
+        let mut buf = [0; 4];
+        assert!(t.read(&mut buf).now_or_never().is_none());
+        assert_eq!(t.stream_position().await.unwrap(), 6);
+
+        // Same with a read into an empty buffer completing it.
+        assert!(t.read(&mut buf).now_or_never().is_none());
+        assert_eq!(t.read(&mut buf[..0]).await.unwrap(), 0);
+        assert_eq!(t.read(&mut buf[..2]).await.unwrap(), 2);
+        assert_eq!(&buf[..2], b"wx");
+        assert_eq!(t.stream_position().await.unwrap(), 8);
+
+        // Seeking and asking for the position again take one seek of the inner object.
+        assert_eq!(t.seek(std::io::SeekFrom::Start(1)).await.unwrap(), 1);
+        assert_eq!(t.stream_position().await.unwrap(), 1);
+        // Writing forgets the position.
+        t.write_all(b"ab").await.unwrap();
+        t.flush().await.unwrap();
+        assert_eq!(t.stream_position().await.unwrap(), 3);
+        t.into_inner().into_inner().done();
+    }
+}
@@ -0,0 +1,236 @@
+use core::fmt;
+
+use ufmt_write::uWrite;
+
+use crate::blocking::WriteAllError;
+
+/// Adapter to [`ufmt::uWrite`](uWrite), for formatting with `ufmt` instead of `core::fmt`.
+///
+/// Each `write_str` writes the whole string with
+/// [`write_all`](crate::blocking::Write::write_all), and an error from the inner writer is
+/// returned as is by `uwrite!`.
+pub struct ToUfmt<T> {
+    inner: T,
+}
+
+impl<T> ToUfmt<T> {
+    /// Create a new adapter.
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+
+    /// Consume the adapter, returning the inner object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T: crate::blocking::Write> uWrite for ToUfmt<T> {
+    type Error = WriteAllError<T::Error>;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.inner.write_all(s.as_bytes())
+    }
+}
+
+/// Error returned when formatting into a full [`UfmtBuffer`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BufferFull;
+
+impl crate::Error for BufferFull {
+    fn kind(&self) -> crate::ErrorKind {
+        crate::ErrorKind::OutOfMemory
+    }
+}
+
+impl fmt::Display for BufferFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BufferFull {}
+
+/// Adapter from an [`asynch::Write`](crate::asynch::Write) to [`ufmt::uWrite`](uWrite), through
+/// a buffer of `N` bytes.
+///
+/// `uwrite!` can't await, so it formats into the buffer, and [`flush`](Self::flush) writes the
+/// buffered text out:
+///
+/// ```
+/// # use embedded_io::adapters::{block_on, Spin};
+/// use embedded_io::adapters::UfmtBuffer;
+/// use ufmt::uwriteln;
+///
+/// # let mut uart = [0; 16];
+/// # let temp = 21;
+/// # block_on(async {
+/// let mut out = UfmtBuffer::<_, 64>::new(&mut uart[..]);
+/// uwriteln!(out, "temp = {}", temp).unwrap();
+/// out.flush().await.unwrap();
+/// # }, &mut Spin);
+/// # assert_eq!(&uart[..10], b"temp = 21\n");
+/// ```
+///
+/// A string that doesn't fit in the rest of the buffer fails with [`BufferFull`], and isn't
+/// buffered. What was formatted before it stays buffered.
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "ufmt", feature = "async"))))]
+pub struct UfmtBuffer<T, const N: usize> {
+    inner: T,
+    buf: [u8; N],
+    len: usize,
+}
+
+#[cfg(feature = "async")]
+impl<T, const N: usize> UfmtBuffer<T, N> {
+    /// Create a new adapter.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Consume the adapter, returning the inner object.
+    ///
+    /// Text still buffered is lost.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// The text buffered and not yet written.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T: crate::asynch::Write, const N: usize> UfmtBuffer<T, N> {
+    /// Write the buffered text to the inner writer, and flush it.
+    ///
+    /// If this fails, the text that wasn't written stays buffered.
+    pub async fn flush(&mut self) -> Result<(), WriteAllError<T::Error>> {
+        while self.len > 0 {
+            match self.inner.write(&self.buf[..self.len]).await {
+                Ok(0) => return Err(WriteAllError::WriteZero),
+                Ok(n) => {
+                    self.buf.copy_within(n..self.len, 0);
+                    self.len -= n;
+                }
+                Err(e) => return Err(WriteAllError::Other(e)),
+            }
+        }
+        self.inner.flush().await.map_err(WriteAllError::Other)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<T, const N: usize> uWrite for UfmtBuffer<T, N> {
+    type Error = BufferFull;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        let buf = self
+            .buf
+            .get_mut(self.len..self.len + s.len())
+            .ok_or(BufferFull)?;
+        buf.copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ufmt::uwrite;
+
+    #[test]
+    fn to_ufmt() {
+        let mut buf = [0; 8];
+        let mut w = ToUfmt::new(&mut buf[..]);
+        assert_eq!(uwrite!(w, "{}-{}", 12, -3), Ok(()));
+        assert_eq!(uwrite!(w, "{}", 4567), Err(WriteAllError::WriteZero));
+        assert_eq!(w.into_inner(), b"");
+        assert_eq!(buf, *b"12--3456");
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn buffer_full_then_flush() {
+        use crate::adapters::{block_on, Spin};
+        use ufmt::uwriteln;
+
+        let mut storage = [0; 16];
+        let mut out = UfmtBuffer::<_, 8>::new(&mut storage[..]);
+        assert_eq!(uwrite!(out, "{}", "abcde"), Ok(()));
+        // The string that doesn't fit isn't buffered; what came before it is.
+        assert_eq!(uwrite!(out, "{}", "fghi"), Err(BufferFull));
+        assert_eq!(out.buffer(), b"abcde");
+        assert_eq!(uwrite!(out, "{}{}", "fg", "hij"), Err(BufferFull));
+        assert_eq!(out.buffer(), b"abcdefg");
+
+        assert_eq!(block_on(out.flush(), &mut Spin), Ok(()));
+        assert_eq!(out.buffer(), b"");
+        assert_eq!(uwriteln!(out, "{}", 1234567), Ok(()));
+        assert_eq!(block_on(out.flush(), &mut Spin), Ok(()));
+        assert_eq!(&storage[..], b"abcdefg1234567\n\0");
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn flush_short_writes_and_errors() {
+        use crate::adapters::block_on;
+        use crate::chaos::{Chaotic, ChaoticError, Fault};
+        use crate::ErrorKind;
+
+        let mut storage = [0; 4];
+        let faults = [
+            Fault::limit(2).with_pending(1),
+            Fault::error(ErrorKind::TimedOut),
+        ];
+        let w = Chaotic::new(&mut storage[..], faults.into_iter());
+        let mut out = UfmtBuffer::<_, 8>::new(w);
+        assert_eq!(uwrite!(out, "{}", "abcdef"), Ok(()));
+        let mut polls = 0;
+        assert_eq!(
+            block_on(out.flush(), &mut || polls += 1),
+            Err(WriteAllError::Other(ChaoticError::Injected(
+                ErrorKind::TimedOut
+            )))
+        );
+        assert_eq!(polls, 1);
+        // What wasn't written stays buffered, and a later flush resumes from it.
+        assert_eq!(out.buffer(), b"cdef");
+        assert_eq!(
+            block_on(out.flush(), &mut || polls += 1),
+            Err(WriteAllError::WriteZero)
+        );
+        assert_eq!(out.buffer(), b"ef");
+        drop(out);
+        assert_eq!(storage, *b"abcd");
+    }
+}
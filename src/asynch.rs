@@ -2,6 +2,8 @@
 
 pub use crate::blocking::ReadExactError;
 pub use crate::blocking::WriteAllError;
+use crate::num::{self, Integer, Pad};
+use crate::varint::{self, ReadVarintError, Varint, ZigZag};
 
 ///
 /// Semantics are the same as [`std::io::Read`], check its documentation for details.
@@ -82,6 +84,206 @@ pub trait Seek: crate::Io {
     }
 }
 
+/// Extension trait for reading varints, see the [`varint`](crate::varint) module.
+///
+/// This is implemented for all [`Read`]ers. Bytes are read one at a time, so that nothing
+/// past the varint is consumed.
+pub trait ReadVarint: Read {
+    /// Read an unsigned LEB128 varint.
+    async fn read_varint<T: Varint>(&mut self) -> Result<T, ReadVarintError<Self::Error>> {
+        let mut decoder = varint::Decoder::new();
+        loop {
+            let mut byte = [0];
+            self.read_exact(&mut byte).await?;
+            match decoder.push(byte[0]) {
+                Ok(Some(v)) => return Ok(v),
+                Ok(None) => {}
+                Err(()) => return Err(ReadVarintError::InvalidData),
+            }
+        }
+    }
+
+    /// Read a zigzag-encoded signed LEB128 varint.
+    async fn read_zigzag<T: ZigZag>(&mut self) -> Result<T, ReadVarintError<Self::Error>> {
+        self.read_varint().await.map(T::unzigzag)
+    }
+}
+
+impl<T: ?Sized + Read> ReadVarint for T {}
+
+/// Extension trait for writing varints, see the [`varint`](crate::varint) module.
+///
+/// This is implemented for all [`Write`]rs.
+pub trait WriteVarint: Write {
+    /// Write an unsigned LEB128 varint.
+    async fn write_varint<T: Varint>(
+        &mut self,
+        value: T,
+    ) -> Result<(), WriteAllError<Self::Error>> {
+        let mut buf = [0; varint::MAX_LEN];
+        let n = varint::encode(value, &mut buf);
+        self.write_all(&buf[..n]).await
+    }
+
+    /// Write a zigzag-encoded signed LEB128 varint.
+    async fn write_zigzag<T: ZigZag>(
+        &mut self,
+        value: T,
+    ) -> Result<(), WriteAllError<Self::Error>> {
+        self.write_varint(value.zigzag()).await
+    }
+}
+
+impl<T: ?Sized + Write> WriteVarint for T {}
+
+macro_rules! read_bytes_methods {
+    ($($t:ty: $le:ident, $be:ident;)*) => {$(
+        #[doc = concat!("Read a little-endian `", stringify!($t), "`.")]
+        async fn $le(&mut self) -> Result<$t, ReadExactError<Self::Error>> {
+            Ok(<$t>::from_le_bytes(self.read_array().await?))
+        }
+
+        #[doc = concat!("Read a big-endian `", stringify!($t), "`.")]
+        async fn $be(&mut self) -> Result<$t, ReadExactError<Self::Error>> {
+            Ok(<$t>::from_be_bytes(self.read_array().await?))
+        }
+    )*};
+}
+
+/// Extension trait for reading fixed-size values, like the `byteorder` crate.
+///
+/// This is implemented for all [`Read`]ers.
+pub trait ReadBytesExt: Read {
+    /// Read exactly `N` bytes into an array.
+    async fn read_array<const N: usize>(&mut self) -> Result<[u8; N], ReadExactError<Self::Error>> {
+        let mut buf = [0; N];
+        self.read_exact(&mut buf).await?;
+        Ok(buf)
+    }
+
+    /// Read a `u8`.
+    async fn read_u8(&mut self) -> Result<u8, ReadExactError<Self::Error>> {
+        Ok(u8::from_le_bytes(self.read_array().await?))
+    }
+
+    /// Read an `i8`.
+    async fn read_i8(&mut self) -> Result<i8, ReadExactError<Self::Error>> {
+        Ok(i8::from_le_bytes(self.read_array().await?))
+    }
+
+    read_bytes_methods! {
+        u16: read_u16_le, read_u16_be;
+        i16: read_i16_le, read_i16_be;
+        u32: read_u32_le, read_u32_be;
+        i32: read_i32_le, read_i32_be;
+        u64: read_u64_le, read_u64_be;
+        i64: read_i64_le, read_i64_be;
+        u128: read_u128_le, read_u128_be;
+        i128: read_i128_le, read_i128_be;
+        f32: read_f32_le, read_f32_be;
+        f64: read_f64_le, read_f64_be;
+    }
+}
+
+impl<T: ?Sized + Read> ReadBytesExt for T {}
+
+macro_rules! write_bytes_methods {
+    ($($t:ty: $le:ident, $be:ident;)*) => {$(
+        #[doc = concat!("Write a little-endian `", stringify!($t), "`.")]
+        async fn $le(&mut self, value: $t) -> Result<(), WriteAllError<Self::Error>> {
+            self.write_all(&value.to_le_bytes()).await
+        }
+
+        #[doc = concat!("Write a big-endian `", stringify!($t), "`.")]
+        async fn $be(&mut self, value: $t) -> Result<(), WriteAllError<Self::Error>> {
+            self.write_all(&value.to_be_bytes()).await
+        }
+    )*};
+}
+
+/// Extension trait for writing fixed-size values, like the `byteorder` crate.
+///
+/// This is implemented for all [`Write`]rs.
+pub trait WriteBytesExt: Write {
+    /// Write a `u8`.
+    async fn write_u8(&mut self, value: u8) -> Result<(), WriteAllError<Self::Error>> {
+        self.write_all(&[value]).await
+    }
+
+    /// Write an `i8`.
+    async fn write_i8(&mut self, value: i8) -> Result<(), WriteAllError<Self::Error>> {
+        self.write_all(&value.to_le_bytes()).await
+    }
+
+    write_bytes_methods! {
+        u16: write_u16_le, write_u16_be;
+        i16: write_i16_le, write_i16_be;
+        u32: write_u32_le, write_u32_be;
+        i32: write_i32_le, write_i32_be;
+        u64: write_u64_le, write_u64_be;
+        i64: write_i64_le, write_i64_be;
+        u128: write_u128_le, write_u128_be;
+        i128: write_i128_le, write_i128_be;
+        f32: write_f32_le, write_f32_be;
+        f64: write_f64_le, write_f64_be;
+    }
+}
+
+impl<T: ?Sized + Write> WriteBytesExt for T {}
+
+/// Extension trait for writing integers as text, see the [`num`](crate::num) module.
+///
+/// This is implemented for all [`Write`]rs.
+pub trait WriteNumExt: Write {
+    /// Write an integer in decimal.
+    async fn write_dec<T: Integer>(&mut self, value: T) -> Result<(), WriteAllError<Self::Error>> {
+        self.write_dec_padded(value, 0, Pad::Space).await
+    }
+
+    /// Write an integer in decimal, padded to at least `width` bytes.
+    async fn write_dec_padded<T: Integer>(
+        &mut self,
+        value: T,
+        width: usize,
+        pad: Pad,
+    ) -> Result<(), WriteAllError<Self::Error>> {
+        let num = num::Formatted::dec(value, width, pad);
+        self.write_all(num.sign()).await?;
+        for padding in num.padding(&mut [0; 16]) {
+            self.write_all(padding).await?;
+        }
+        self.write_all(num.digits()).await
+    }
+
+    /// Write an integer in lowercase hex, zero-padded to at least `width` digits.
+    ///
+    /// Negative numbers are written as two's complement, like `{:x}`.
+    async fn write_hex<T: Integer>(
+        &mut self,
+        value: T,
+        width: usize,
+    ) -> Result<(), WriteAllError<Self::Error>> {
+        let num = num::Formatted::hex(value, width);
+        for padding in num.padding(&mut [0; 16]) {
+            self.write_all(padding).await?;
+        }
+        self.write_all(num.digits()).await
+    }
+
+    /// Write bytes in lowercase hex, two digits each, without separators.
+    async fn write_hex_bytes(&mut self, bytes: &[u8]) -> Result<(), WriteAllError<Self::Error>> {
+        let mut buf = [0; 32];
+        for chunk in bytes.chunks(buf.len() / 2) {
+            let out = &mut buf[..chunk.len() * 2];
+            num::hex_bytes(chunk, out);
+            self.write_all(out).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ?Sized + Write> WriteNumExt for T {}
+
 impl<T: ?Sized + Read> Read for &mut T {
     #[inline]
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
@@ -167,7 +369,7 @@ impl Write for &mut [u8] {
     #[inline]
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
         let amt = core::cmp::min(buf.len(), self.len());
-        let (a, b) = core::mem::replace(self, &mut []).split_at_mut(amt);
+        let (a, b) = core::mem::take(self).split_at_mut(amt);
         a.copy_from_slice(&buf[..amt]);
         *self = b;
         Ok(amt)
@@ -229,3 +431,75 @@ impl Write for alloc::vec::Vec<u8> {
         Ok(buf.len())
     }
 }
+
+/// Return `Pending` `n` times, waking the task each time.
+pub(crate) async fn yield_n(mut n: u32) {
+    core::future::poll_fn(|cx| {
+        if n == 0 {
+            core::task::Poll::Ready(())
+        } else {
+            n -= 1;
+            cx.waker().wake_by_ref();
+            core::task::Poll::Pending
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::{block_on, Spin};
+    use crate::chaos::{Chaotic, Fault};
+
+    #[test]
+    fn bytes_ext_round_trip() {
+        let mut buf = [0; 16];
+        let faults = core::iter::repeat(Fault::limit(1).with_pending(1));
+        let mut w = Chaotic::new(&mut buf[..], faults);
+        block_on(
+            async {
+                w.write_u16_be(0x0102).await.unwrap();
+                w.write_i32_le(-2).await.unwrap();
+                w.write_f64_be(2.0).await.unwrap();
+                assert_eq!(w.write_u32_le(0).await, Err(WriteAllError::WriteZero));
+            },
+            &mut Spin,
+        );
+        assert_eq!(&buf[..6], &[0x01, 0x02, 0xFE, 0xFF, 0xFF, 0xFF]);
+
+        let faults = core::iter::repeat(Fault::limit(1).with_pending(1));
+        let mut r = Chaotic::new(&buf[..14], faults);
+        block_on(
+            async {
+                assert_eq!(r.read_u16_be().await, Ok(0x0102));
+                assert_eq!(r.read_i32_le().await, Ok(-2));
+                assert_eq!(r.read_f64_be().await, Ok(2.0));
+                assert_eq!(r.read_u8().await, Err(ReadExactError::UnexpectedEof));
+            },
+            &mut Spin,
+        );
+    }
+
+    #[test]
+    fn num_ext() {
+        let mut buf = [0; 64];
+        let faults = core::iter::repeat(Fault::limit(2).with_pending(1));
+        let mut w = Chaotic::new(&mut buf[..], faults);
+        block_on(
+            async {
+                w.write_dec_padded(i128::MIN, 42, Pad::Space).await.unwrap();
+                w.write_dec_padded(-3i8, 3, Pad::Zero).await.unwrap();
+                w.write_hex(0x1Fu16, 3).await.unwrap();
+                w.write_hex_bytes(&[0x00, 0xFF]).await.unwrap();
+                w.write_dec(0u8).await.unwrap();
+            },
+            &mut Spin,
+        );
+        let len = 64 - w.into_inner().len();
+        assert_eq!(
+            &buf[..len],
+            &b"  -170141183460469231731687303715884105728-0301f00ff0"[..]
+        );
+    }
+}
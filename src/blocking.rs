@@ -2,6 +2,9 @@
 
 use core::fmt;
 
+use crate::num::{self, Integer, Pad};
+use crate::varint::{self, ReadVarintError, Varint, ZigZag};
+
 /// Error returned by [`Read::read_exact`]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -101,6 +104,37 @@ pub trait Read: crate::Io {
             Ok(())
         }
     }
+
+    /// Transform this reader into an iterator over its bytes.
+    ///
+    /// Bytes are read one at a time, so this is slow unless the reader is buffered.
+    fn bytes(self) -> Bytes<Self>
+    where
+        Self: Sized,
+    {
+        Bytes { inner: self }
+    }
+}
+
+/// Iterator over the bytes of a reader, returned by [`Read::bytes`].
+///
+/// Yields `Err` for each failed read, and ends at EOF.
+#[derive(Debug)]
+pub struct Bytes<R> {
+    inner: R,
+}
+
+impl<R: Read> Iterator for Bytes<R> {
+    type Item = Result<u8, R::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut byte = 0;
+        match self.inner.read(core::slice::from_mut(&mut byte)) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(byte)),
+            Err(e) => Some(Err(e)),
+        }
+    }
 }
 
 /// Blocking buffered reader.
@@ -194,6 +228,225 @@ pub trait Seek: crate::Io {
     }
 }
 
+/// Get whether a reader is ready.
+///
+/// This allows using a [`Read`] without blocking, by only calling [`read`](Read::read) when
+/// it's ready.
+pub trait ReadReady: crate::Io {
+    /// Get whether the reader is ready for immediately reading.
+    ///
+    /// This usually means that some bytes have been received and are buffered, or that the
+    /// reader is at EOF. If this returns `true`, the next call to [`read`](Read::read) won't
+    /// block.
+    fn read_ready(&mut self) -> Result<bool, Self::Error>;
+}
+
+/// Get whether a writer is ready.
+///
+/// This allows using a [`Write`] without blocking, by only calling [`write`](Write::write)
+/// when it's ready.
+pub trait WriteReady: crate::Io {
+    /// Get whether the writer is ready for immediately writing.
+    ///
+    /// This usually means that there is free space in the transmit buffer. If this returns
+    /// `true`, the next call to [`write`](Write::write) won't block.
+    fn write_ready(&mut self) -> Result<bool, Self::Error>;
+}
+
+/// Extension trait for reading varints, see the [`varint`](crate::varint) module.
+///
+/// This is implemented for all [`Read`]ers. Bytes are read one at a time, so that nothing
+/// past the varint is consumed.
+pub trait ReadVarint: Read {
+    /// Read an unsigned LEB128 varint.
+    fn read_varint<T: Varint>(&mut self) -> Result<T, ReadVarintError<Self::Error>> {
+        let mut decoder = varint::Decoder::new();
+        loop {
+            let mut byte = [0];
+            self.read_exact(&mut byte)?;
+            match decoder.push(byte[0]) {
+                Ok(Some(v)) => return Ok(v),
+                Ok(None) => {}
+                Err(()) => return Err(ReadVarintError::InvalidData),
+            }
+        }
+    }
+
+    /// Read a zigzag-encoded signed LEB128 varint.
+    fn read_zigzag<T: ZigZag>(&mut self) -> Result<T, ReadVarintError<Self::Error>> {
+        self.read_varint().map(T::unzigzag)
+    }
+}
+
+impl<T: ?Sized + Read> ReadVarint for T {}
+
+/// Extension trait for writing varints, see the [`varint`](crate::varint) module.
+///
+/// This is implemented for all [`Write`]rs.
+pub trait WriteVarint: Write {
+    /// Write an unsigned LEB128 varint.
+    fn write_varint<T: Varint>(&mut self, value: T) -> Result<(), WriteAllError<Self::Error>> {
+        let mut buf = [0; varint::MAX_LEN];
+        let n = varint::encode(value, &mut buf);
+        self.write_all(&buf[..n])
+    }
+
+    /// Write a zigzag-encoded signed LEB128 varint.
+    fn write_zigzag<T: ZigZag>(&mut self, value: T) -> Result<(), WriteAllError<Self::Error>> {
+        self.write_varint(value.zigzag())
+    }
+}
+
+impl<T: ?Sized + Write> WriteVarint for T {}
+
+macro_rules! read_bytes_methods {
+    ($($t:ty: $le:ident, $be:ident;)*) => {$(
+        #[doc = concat!("Read a little-endian `", stringify!($t), "`.")]
+        fn $le(&mut self) -> Result<$t, ReadExactError<Self::Error>> {
+            Ok(<$t>::from_le_bytes(self.read_array()?))
+        }
+
+        #[doc = concat!("Read a big-endian `", stringify!($t), "`.")]
+        fn $be(&mut self) -> Result<$t, ReadExactError<Self::Error>> {
+            Ok(<$t>::from_be_bytes(self.read_array()?))
+        }
+    )*};
+}
+
+/// Extension trait for reading fixed-size values, like the `byteorder` crate.
+///
+/// This is implemented for all [`Read`]ers.
+pub trait ReadBytesExt: Read {
+    /// Read exactly `N` bytes into an array.
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], ReadExactError<Self::Error>> {
+        let mut buf = [0; N];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Read a `u8`.
+    fn read_u8(&mut self) -> Result<u8, ReadExactError<Self::Error>> {
+        Ok(u8::from_le_bytes(self.read_array()?))
+    }
+
+    /// Read an `i8`.
+    fn read_i8(&mut self) -> Result<i8, ReadExactError<Self::Error>> {
+        Ok(i8::from_le_bytes(self.read_array()?))
+    }
+
+    read_bytes_methods! {
+        u16: read_u16_le, read_u16_be;
+        i16: read_i16_le, read_i16_be;
+        u32: read_u32_le, read_u32_be;
+        i32: read_i32_le, read_i32_be;
+        u64: read_u64_le, read_u64_be;
+        i64: read_i64_le, read_i64_be;
+        u128: read_u128_le, read_u128_be;
+        i128: read_i128_le, read_i128_be;
+        f32: read_f32_le, read_f32_be;
+        f64: read_f64_le, read_f64_be;
+    }
+}
+
+impl<T: ?Sized + Read> ReadBytesExt for T {}
+
+macro_rules! write_bytes_methods {
+    ($($t:ty: $le:ident, $be:ident;)*) => {$(
+        #[doc = concat!("Write a little-endian `", stringify!($t), "`.")]
+        fn $le(&mut self, value: $t) -> Result<(), WriteAllError<Self::Error>> {
+            self.write_all(&value.to_le_bytes())
+        }
+
+        #[doc = concat!("Write a big-endian `", stringify!($t), "`.")]
+        fn $be(&mut self, value: $t) -> Result<(), WriteAllError<Self::Error>> {
+            self.write_all(&value.to_be_bytes())
+        }
+    )*};
+}
+
+/// Extension trait for writing fixed-size values, like the `byteorder` crate.
+///
+/// This is implemented for all [`Write`]rs.
+pub trait WriteBytesExt: Write {
+    /// Write a `u8`.
+    fn write_u8(&mut self, value: u8) -> Result<(), WriteAllError<Self::Error>> {
+        self.write_all(&[value])
+    }
+
+    /// Write an `i8`.
+    fn write_i8(&mut self, value: i8) -> Result<(), WriteAllError<Self::Error>> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    write_bytes_methods! {
+        u16: write_u16_le, write_u16_be;
+        i16: write_i16_le, write_i16_be;
+        u32: write_u32_le, write_u32_be;
+        i32: write_i32_le, write_i32_be;
+        u64: write_u64_le, write_u64_be;
+        i64: write_i64_le, write_i64_be;
+        u128: write_u128_le, write_u128_be;
+        i128: write_i128_le, write_i128_be;
+        f32: write_f32_le, write_f32_be;
+        f64: write_f64_le, write_f64_be;
+    }
+}
+
+impl<T: ?Sized + Write> WriteBytesExt for T {}
+
+/// Extension trait for writing integers as text, see the [`num`](crate::num) module.
+///
+/// This is implemented for all [`Write`]rs.
+pub trait WriteNumExt: Write {
+    /// Write an integer in decimal.
+    fn write_dec<T: Integer>(&mut self, value: T) -> Result<(), WriteAllError<Self::Error>> {
+        self.write_dec_padded(value, 0, Pad::Space)
+    }
+
+    /// Write an integer in decimal, padded to at least `width` bytes.
+    fn write_dec_padded<T: Integer>(
+        &mut self,
+        value: T,
+        width: usize,
+        pad: Pad,
+    ) -> Result<(), WriteAllError<Self::Error>> {
+        let num = num::Formatted::dec(value, width, pad);
+        self.write_all(num.sign())?;
+        for padding in num.padding(&mut [0; 16]) {
+            self.write_all(padding)?;
+        }
+        self.write_all(num.digits())
+    }
+
+    /// Write an integer in lowercase hex, zero-padded to at least `width` digits.
+    ///
+    /// Negative numbers are written as two's complement, like `{:x}`.
+    fn write_hex<T: Integer>(
+        &mut self,
+        value: T,
+        width: usize,
+    ) -> Result<(), WriteAllError<Self::Error>> {
+        let num = num::Formatted::hex(value, width);
+        for padding in num.padding(&mut [0; 16]) {
+            self.write_all(padding)?;
+        }
+        self.write_all(num.digits())
+    }
+
+    /// Write bytes in lowercase hex, two digits each, without separators.
+    fn write_hex_bytes(&mut self, bytes: &[u8]) -> Result<(), WriteAllError<Self::Error>> {
+        let mut buf = [0; 32];
+        for chunk in bytes.chunks(buf.len() / 2) {
+            let out = &mut buf[..chunk.len() * 2];
+            num::hex_bytes(chunk, out);
+            self.write_all(out)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ?Sized + Write> WriteNumExt for T {}
+
 impl<T: ?Sized + Read> Read for &mut T {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
@@ -230,6 +483,20 @@ impl<T: ?Sized + Seek> Seek for &mut T {
     }
 }
 
+impl<T: ?Sized + ReadReady> ReadReady for &mut T {
+    #[inline]
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        T::read_ready(self)
+    }
+}
+
+impl<T: ?Sized + WriteReady> WriteReady for &mut T {
+    #[inline]
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        T::write_ready(self)
+    }
+}
+
 /// Read is implemented for `&[u8]` by copying from the slice.
 ///
 /// Note that reading updates the slice to point to the yet unread part.
@@ -266,6 +533,13 @@ impl BufRead for &[u8] {
     }
 }
 
+impl ReadReady for &[u8] {
+    #[inline]
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
 /// Write is implemented for `&mut [u8]` by copying into the slice, overwriting
 /// its data.
 ///
@@ -279,7 +553,7 @@ impl Write for &mut [u8] {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
         let amt = core::cmp::min(buf.len(), self.len());
-        let (a, b) = core::mem::replace(self, &mut []).split_at_mut(amt);
+        let (a, b) = core::mem::take(self).split_at_mut(amt);
         a.copy_from_slice(&buf[..amt]);
         *self = b;
         Ok(amt)
@@ -291,6 +565,13 @@ impl Write for &mut [u8] {
     }
 }
 
+impl WriteReady for &mut [u8] {
+    #[inline]
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
 #[cfg(feature = "alloc")]
 #[cfg_attr(docsrs, doc(cfg(any(feature = "std", feature = "alloc"))))]
 impl<T: ?Sized + Read> Read for alloc::boxed::Box<T> {
@@ -335,6 +616,24 @@ impl<T: ?Sized + Seek> Seek for alloc::boxed::Box<T> {
     }
 }
 
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "std", feature = "alloc"))))]
+impl<T: ?Sized + ReadReady> ReadReady for alloc::boxed::Box<T> {
+    #[inline]
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        T::read_ready(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "std", feature = "alloc"))))]
+impl<T: ?Sized + WriteReady> WriteReady for alloc::boxed::Box<T> {
+    #[inline]
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        T::write_ready(self)
+    }
+}
+
 #[cfg(feature = "alloc")]
 #[cfg_attr(docsrs, doc(cfg(any(feature = "std", feature = "alloc"))))]
 impl Write for alloc::vec::Vec<u8> {
@@ -349,3 +648,146 @@ impl Write for alloc::vec::Vec<u8> {
         Ok(())
     }
 }
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "std", feature = "alloc"))))]
+impl WriteReady for alloc::vec::Vec<u8> {
+    #[inline]
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chaos::{Chaotic, ChaoticError, Fault};
+
+    #[test]
+    fn bytes_ext_round_trip() {
+        let mut buf = [0; 64];
+        let mut w = &mut buf[..];
+        w.write_u8(0x01).unwrap();
+        w.write_i8(-2).unwrap();
+        w.write_u16_le(0x0203).unwrap();
+        w.write_u16_be(0x0203).unwrap();
+        w.write_i32_le(-0x0405_0607).unwrap();
+        w.write_u64_be(0x0809_0A0B_0C0D_0E0F).unwrap();
+        w.write_i128_le(i128::MIN).unwrap();
+        w.write_f32_be(1.5).unwrap();
+        w.write_f64_le(-0.25).unwrap();
+        let len = 64 - w.len();
+        assert_eq!(len, 1 + 1 + 2 + 2 + 4 + 8 + 16 + 4 + 8);
+        assert_eq!(&buf[..6], &[0x01, 0xFE, 0x03, 0x02, 0x02, 0x03]);
+        assert_eq!(&buf[10..18], &[8, 9, 10, 11, 12, 13, 14, 15]);
+        assert_eq!(&buf[34..38], &[0x3F, 0xC0, 0x00, 0x00]);
+
+        let mut r = &buf[..len];
+        assert_eq!(r.read_u8(), Ok(0x01));
+        assert_eq!(r.read_i8(), Ok(-2));
+        assert_eq!(r.read_u16_le(), Ok(0x0203));
+        assert_eq!(r.read_u16_be(), Ok(0x0203));
+        assert_eq!(r.read_i32_le(), Ok(-0x0405_0607));
+        assert_eq!(r.read_u64_be(), Ok(0x0809_0A0B_0C0D_0E0F));
+        assert_eq!(r.read_i128_le(), Ok(i128::MIN));
+        assert_eq!(r.read_f32_be(), Ok(1.5));
+        assert_eq!(r.read_f64_le(), Ok(-0.25));
+        assert_eq!(r.read_u8(), Err(ReadExactError::UnexpectedEof));
+    }
+
+    #[test]
+    fn bytes_ext_short_transfers() {
+        let faults = core::iter::repeat(Fault::limit(1));
+        let mut r = Chaotic::new(&[1, 2, 3, 4, 5][..], faults);
+        assert_eq!(r.read_u32_be(), Ok(0x0102_0304));
+        assert_eq!(r.read_array::<2>(), Err(ReadExactError::UnexpectedEof));
+
+        let mut buf = [0; 6];
+        let faults = core::iter::repeat(Fault::limit(1));
+        let mut w = Chaotic::new(&mut buf[..], faults);
+        w.write_u32_le(0x0102_0304).unwrap();
+        assert_eq!(w.write_u32_le(0), Err(WriteAllError::WriteZero));
+        assert_eq!(buf, [4, 3, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn bytes_ext_errors() {
+        let faults = [Fault::NONE, Fault::error(crate::ErrorKind::TimedOut)];
+        let mut r = Chaotic::new(&[1, 2, 3, 4][..], faults.into_iter());
+        assert_eq!(r.read_u16_le(), Ok(0x0201));
+        assert_eq!(
+            r.read_u16_le(),
+            Err(ReadExactError::Other(ChaoticError::Injected(
+                crate::ErrorKind::TimedOut
+            )))
+        );
+    }
+
+    #[test]
+    fn bytes() {
+        let r = Chaotic::new(&b"abc"[..], core::iter::repeat(Fault::limit(2)));
+        let mut bytes = r.bytes();
+        assert_eq!(bytes.next(), Some(Ok(b'a')));
+        assert_eq!(bytes.next(), Some(Ok(b'b')));
+        assert_eq!(bytes.next(), Some(Ok(b'c')));
+        assert_eq!(bytes.next(), None);
+        assert_eq!(bytes.next(), None);
+
+        // Errors are yielded without ending the iterator.
+        let faults = [Fault::NONE, Fault::error(crate::ErrorKind::TimedOut)];
+        let mut bytes = Chaotic::new(&b"ab"[..], faults.into_iter()).bytes();
+        assert_eq!(bytes.next(), Some(Ok(b'a')));
+        assert_eq!(
+            bytes.next(),
+            Some(Err(ChaoticError::Injected(crate::ErrorKind::TimedOut)))
+        );
+        assert_eq!(bytes.next(), Some(Ok(b'b')));
+        assert_eq!(bytes.next(), None);
+
+        // Reading through a reference leaves the rest in the reader.
+        let mut r = &b"xyz"[..];
+        assert_eq!((&mut r).bytes().next(), Some(Ok(b'x')));
+        assert_eq!(r, b"yz");
+    }
+
+    #[test]
+    fn num_ext() {
+        let mut buf = [0; 128];
+        let mut w = &mut buf[..];
+        w.write_dec(-42i8).unwrap();
+        w.write_all(b",").unwrap();
+        w.write_dec_padded(-42i32, 6, Pad::Zero).unwrap();
+        w.write_all(b",").unwrap();
+        w.write_dec_padded(42u8, 4, Pad::Space).unwrap();
+        w.write_all(b",").unwrap();
+        w.write_dec(i128::MIN).unwrap();
+        w.write_all(b",").unwrap();
+        w.write_hex(0xABu8, 4).unwrap();
+        w.write_all(b",").unwrap();
+        w.write_hex(-2i32, 0).unwrap();
+        w.write_all(b",").unwrap();
+        w.write_hex_bytes(&[0xDE, 0xAD, 0x01]).unwrap();
+        let len = 128 - w.len();
+        assert_eq!(
+            &buf[..len],
+            &b"-42,-00042,  42,-170141183460469231731687303715884105728,00ab,fffffffe,dead01"[..]
+        );
+    }
+
+    #[test]
+    fn num_ext_short_writes() {
+        // Padding wider than the internal chunk, and more hex bytes than fit in one chunk.
+        let mut buf = [0; 80];
+        let faults = core::iter::repeat(Fault::limit(3));
+        let mut w = Chaotic::new(&mut buf[..], faults);
+        w.write_dec_padded(-7i64, 20, Pad::Zero).unwrap();
+        w.write_hex_bytes(&[0x5A; 20]).unwrap();
+        assert_eq!(&buf[..20], b"-0000000000000000007");
+        assert!(buf[20..60].chunks(2).all(|pair| pair == b"5a"));
+
+        let mut buf = [0; 3];
+        let mut w = &mut buf[..];
+        assert_eq!(w.write_dec(1234u16), Err(WriteAllError::WriteZero));
+        assert_eq!(&buf, b"123");
+    }
+}
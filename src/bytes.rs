@@ -0,0 +1,391 @@
+//! Integration with the [`bytes`] crate.
+//!
+//! [`Reader`] reads from any [`Buf`], and [`Writer`] writes to any [`BufMut`]. [`BytesMut`]
+//! also implements [`Write`](crate::blocking::Write) directly, growing as needed.
+//!
+//! [`read_buf_mut`] reads from any reader directly into the spare capacity of a [`BufMut`].
+
+use bytes::{Buf, BufMut, BytesMut};
+
+/// Reader over a [`Buf`].
+///
+/// Reading advances the buffer. Reads return `Ok(0)` once it has no bytes remaining.
+///
+/// [`BufRead::fill_buf`](crate::blocking::BufRead::fill_buf) returns the buffer's current
+/// [`chunk`](Buf::chunk), which may be shorter than what remains if the buffer isn't contiguous,
+/// for example a [`Chain`](bytes::buf::Chain).
+#[derive(Clone, Debug)]
+pub struct Reader<B> {
+    inner: B,
+}
+
+impl<B: Buf> Reader<B> {
+    /// Create a new reader.
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+
+    /// Consume the reader, returning the inner buffer.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Borrow the inner buffer.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner buffer.
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    fn copy_to(&mut self, buf: &mut [u8]) -> usize {
+        let amt = core::cmp::min(buf.len(), self.inner.remaining());
+        self.inner.copy_to_slice(&mut buf[..amt]);
+        amt
+    }
+}
+
+impl<B> crate::Io for Reader<B> {
+    type Error = core::convert::Infallible;
+}
+
+impl<B: Buf> crate::blocking::Read for Reader<B> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(self.copy_to(buf))
+    }
+}
+
+impl<B: Buf> crate::blocking::BufRead for Reader<B> {
+    #[inline]
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        Ok(self.inner.chunk())
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.inner.advance(amt)
+    }
+}
+
+impl<B: Buf> crate::blocking::ReadReady for Reader<B> {
+    #[inline]
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<B: Buf> crate::asynch::Read for Reader<B> {
+    #[inline]
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(self.copy_to(buf))
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<B: Buf> crate::asynch::BufRead for Reader<B> {
+    #[inline]
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        Ok(self.inner.chunk())
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.inner.advance(amt)
+    }
+}
+
+/// Writer over a [`BufMut`].
+///
+/// Writing appends to the buffer. If the buffer has limited capacity, like `&mut [u8]`, write
+/// operations return short writes once it's full: ultimately, `Ok(0)`; in this situation,
+/// `write_all` returns an error of kind `ErrorKind::WriteZero`.
+#[derive(Clone, Debug)]
+pub struct Writer<B> {
+    inner: B,
+}
+
+impl<B: BufMut> Writer<B> {
+    /// Create a new writer.
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+
+    /// Consume the writer, returning the inner buffer.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    /// Borrow the inner buffer.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner buffer.
+    pub fn inner_mut(&mut self) -> &mut B {
+        &mut self.inner
+    }
+
+    fn put(&mut self, buf: &[u8]) -> usize {
+        let amt = core::cmp::min(buf.len(), self.inner.remaining_mut());
+        self.inner.put_slice(&buf[..amt]);
+        amt
+    }
+}
+
+impl<B> crate::Io for Writer<B> {
+    type Error = core::convert::Infallible;
+}
+
+impl<B: BufMut> crate::blocking::Write for Writer<B> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(self.put(buf))
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<B: BufMut> crate::blocking::WriteReady for Writer<B> {
+    #[inline]
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<B: BufMut> crate::asynch::Write for Writer<B> {
+    #[inline]
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(self.put(buf))
+    }
+}
+
+impl crate::Io for BytesMut {
+    type Error = core::convert::Infallible;
+}
+
+/// Write is implemented for `BytesMut` by appending to it, growing it as needed.
+impl crate::blocking::Write for BytesMut {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl crate::blocking::WriteReady for BytesMut {
+    #[inline]
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl crate::asynch::Write for BytesMut {
+    #[inline]
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+/// Zero the buffer's next chunk of spare capacity, and return it as a slice to read into.
+fn spare<B: BufMut + ?Sized>(buf: &mut B) -> &mut [u8] {
+    let chunk = buf.chunk_mut();
+    let len = chunk.len();
+    let ptr = chunk.as_mut_ptr();
+    // Safety: the chunk is valid for writes of `len` bytes, and initialized by `write_bytes`.
+    unsafe {
+        core::ptr::write_bytes(ptr, 0, len);
+        core::slice::from_raw_parts_mut(ptr, len)
+    }
+}
+
+/// Read some bytes directly into the spare capacity of `buf`, advancing it by the number of
+/// bytes read.
+///
+/// At most one chunk of spare capacity is filled, as returned by [`BufMut::chunk_mut`]; a
+/// [`BytesMut`] reserves more capacity if it's full. Returns `Ok(0)` at EOF, or if `buf` has no
+/// capacity remaining.
+pub fn read_buf_mut<R: crate::blocking::Read + ?Sized, B: BufMut + ?Sized>(
+    reader: &mut R,
+    buf: &mut B,
+) -> Result<usize, R::Error> {
+    if !buf.has_remaining_mut() {
+        return Ok(0);
+    }
+    let chunk = spare(buf);
+    let len = chunk.len();
+    let n = reader.read(chunk)?;
+    assert!(n <= len, "reader returned more bytes than requested");
+    // Safety: `n` bytes of the chunk were initialized, by `spare` and the read.
+    unsafe { buf.advance_mut(n) };
+    Ok(n)
+}
+
+/// Async version of [`read_buf_mut`].
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub async fn read_buf_mut_async<R: crate::asynch::Read + ?Sized, B: BufMut + ?Sized>(
+    reader: &mut R,
+    buf: &mut B,
+) -> Result<usize, R::Error> {
+    if !buf.has_remaining_mut() {
+        return Ok(0);
+    }
+    let chunk = spare(buf);
+    let len = chunk.len();
+    let n = reader.read(chunk).await?;
+    assert!(n <= len, "reader returned more bytes than requested");
+    // Safety: `n` bytes of the chunk were initialized, by `spare` and the read.
+    unsafe { buf.advance_mut(n) };
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::{BufRead, Read, Write, WriteAllError};
+    use crate::chaos::{Chaotic, ChaoticError, Fault};
+    use crate::ErrorKind;
+
+    #[test]
+    fn reader() {
+        let mut r = Reader::new((&b"ab"[..]).chain(&b"cd"[..]));
+        assert_eq!(r.fill_buf(), Ok(&b"ab"[..]));
+        r.consume(1);
+        assert_eq!(r.fill_buf(), Ok(&b"b"[..]));
+        let mut buf = [0; 8];
+        assert_eq!(r.read(&mut buf), Ok(3));
+        assert_eq!(&buf[..3], b"bcd");
+        assert_eq!(r.read(&mut buf), Ok(0));
+        assert_eq!(r.fill_buf(), Ok(&b""[..]));
+    }
+
+    #[test]
+    fn writer() {
+        let mut storage = [0; 4];
+        let mut w = Writer::new(&mut storage[..]);
+        assert_eq!(w.write(b"abc"), Ok(3));
+        assert_eq!(w.write(b"de"), Ok(1));
+        assert_eq!(w.write(b"f"), Ok(0));
+        assert_eq!(w.flush(), Ok(()));
+        assert_eq!(&storage, b"abcd");
+
+        let mut w = Writer::new(BytesMut::new().limit(3));
+        assert_eq!(w.write_all(b"abcd"), Err(WriteAllError::WriteZero));
+        assert_eq!(&w.into_inner().into_inner()[..], b"abc");
+    }
+
+    #[test]
+    fn bytes_mut() {
+        let mut buf = BytesMut::new();
+        assert_eq!(buf.write(b"abc"), Ok(3));
+        assert_eq!(buf.write(&[b'd'; 100]), Ok(100));
+        assert_eq!(buf.len(), 103);
+        assert_eq!(&buf[..4], b"abcd");
+    }
+
+    #[test]
+    fn read_into_spare_capacity() {
+        let mut r = &b"hello"[..];
+        let mut buf = BytesMut::new();
+        assert_eq!(read_buf_mut(&mut r, &mut buf), Ok(5));
+        assert_eq!(read_buf_mut(&mut r, &mut buf), Ok(0));
+        assert_eq!(&buf[..], b"hello");
+
+        // Spare capacity left over from earlier contents is read into again.
+        buf.clear();
+        let mut r = &b"xy"[..];
+        assert_eq!(read_buf_mut(&mut r, &mut buf), Ok(2));
+        assert_eq!(&buf[..], b"xy");
+
+        // Only the first chunk is filled.
+        let (mut a, mut b) = ([0; 2], [0; 4]);
+        let mut dst = (&mut a[..]).chain_mut(&mut b[..]);
+        let mut r = &b"abcdef"[..];
+        assert_eq!(read_buf_mut(&mut r, &mut dst), Ok(2));
+        assert_eq!(read_buf_mut(&mut r, &mut dst), Ok(4));
+        assert_eq!(read_buf_mut(&mut r, &mut dst), Ok(0));
+        assert_eq!(r, b"");
+        assert_eq!((&a, &b), (b"ab", b"cdef"));
+    }
+
+    #[test]
+    fn read_into_full_or_failing() {
+        // A full buffer doesn't touch the reader.
+        let faults = [Fault::error(ErrorKind::Other)];
+        let mut r = Chaotic::new(&b"abc"[..], faults.into_iter());
+        let mut storage = [0; 0];
+        assert_eq!(read_buf_mut(&mut r, &mut &mut storage[..]), Ok(0));
+
+        let mut buf = BytesMut::new();
+        assert_eq!(
+            read_buf_mut(&mut r, &mut buf),
+            Err(ChaoticError::Injected(ErrorKind::Other))
+        );
+        assert!(buf.is_empty());
+        assert_eq!(read_buf_mut(&mut r, &mut buf), Ok(3));
+        assert_eq!(&buf[..], b"abc");
+    }
+
+    struct Liar;
+
+    impl crate::Io for Liar {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Read for Liar {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            Ok(buf.len() + 1)
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "reader returned more bytes than requested")]
+    fn read_too_much() {
+        let mut storage = [0; 4];
+        let _ = read_buf_mut(&mut Liar, &mut &mut storage[..]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::block_on;
+
+        let faults = core::iter::repeat(Fault::limit(2).with_pending(1));
+        let mut r = Chaotic::new(&b"abc"[..], faults);
+        let mut buf = BytesMut::new();
+        let mut polls = 0;
+        let read = read_buf_mut_async(&mut r, &mut buf);
+        assert_eq!(block_on(read, &mut || polls += 1), Ok(2));
+        assert_eq!(polls, 1);
+
+        let mut r = Reader::new(&b"xyz"[..]);
+        let mut dst = [0; 2];
+        let read = crate::asynch::Read::read(&mut r, &mut dst);
+        assert_eq!(block_on(read, &mut || polls += 1), Ok(2));
+        let fill = crate::asynch::BufRead::fill_buf(&mut r);
+        assert_eq!(block_on(fill, &mut || polls += 1), Ok(&b"z"[..]));
+        let write = crate::asynch::Write::write(&mut buf, b"def");
+        assert_eq!(block_on(write, &mut || polls += 1), Ok(3));
+        assert_eq!(&buf[..], b"abdef");
+        assert_eq!(polls, 1);
+    }
+}
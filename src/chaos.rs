@@ -0,0 +1,439 @@
+//! Fault injection, for testing code against misbehaving IO objects.
+//!
+//! In-memory IO objects like `&[u8]` or `Vec<u8>` always transfer as much as they're asked to,
+//! which hides bugs in code that doesn't handle short reads and writes. Wrapping them in
+//! [`Chaotic`] makes each operation subject to a [`Fault`]: a cap on the transfer size, an
+//! injected error, or, for async operations, spurious `Pending`s.
+//!
+//! Faults are taken from any `Iterator<Item = Fault>`. Use [`Random`] for a reproducible
+//! pseudo-random sequence, or an explicit list for a precise schedule. Once the iterator is
+//! exhausted, operations pass through unaffected.
+
+use core::fmt;
+
+use crate::ErrorKind;
+
+/// Faults applied to a single operation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Fault {
+    /// Maximum number of bytes transferred.
+    ///
+    /// A limit of zero is treated as one, since a zero-length transfer means EOF for reads,
+    /// and is an error for writes.
+    pub limit: usize,
+    /// Fail the operation with this error instead of performing it.
+    pub error: Option<ErrorKind>,
+    /// Number of times async operations return `Pending` before making progress.
+    pub pending: u32,
+}
+
+impl Fault {
+    /// No fault: the operation is performed unaffected.
+    pub const NONE: Fault = Fault {
+        limit: usize::MAX,
+        error: None,
+        pending: 0,
+    };
+
+    /// Cap the transfer to `limit` bytes.
+    pub const fn limit(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Self::NONE
+        }
+    }
+
+    /// Fail with an error of kind `kind`.
+    pub const fn error(kind: ErrorKind) -> Self {
+        Self {
+            error: Some(kind),
+            ..Self::NONE
+        }
+    }
+
+    /// Return `Pending` `pending` times before proceeding.
+    pub const fn with_pending(self, pending: u32) -> Self {
+        Self { pending, ..self }
+    }
+}
+
+impl Default for Fault {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Seedable pseudo-random fault generator.
+///
+/// This is an infinite iterator of [`Fault`]s. The same seed and settings always produce the
+/// same sequence, so failing tests can be replayed.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+    max_limit: usize,
+    error_kind: ErrorKind,
+    error_one_in: u32,
+    max_pending: u32,
+}
+
+impl Random {
+    /// Create a new generator.
+    ///
+    /// By default, transfers are capped to a random size between 1 and 16 bytes, and no
+    /// errors or `Pending`s are injected.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            max_limit: 16,
+            error_kind: ErrorKind::Other,
+            error_one_in: 0,
+            max_pending: 0,
+        }
+    }
+
+    /// Cap transfers to a random size between 1 and `max_limit` bytes.
+    pub fn max_limit(self, max_limit: usize) -> Self {
+        Self { max_limit, ..self }
+    }
+
+    /// Fail one in `one_in` operations, on average, with an error of kind `kind`.
+    ///
+    /// Zero disables error injection.
+    pub fn errors(self, kind: ErrorKind, one_in: u32) -> Self {
+        Self {
+            error_kind: kind,
+            error_one_in: one_in,
+            ..self
+        }
+    }
+
+    /// Return `Pending` between 0 and `max_pending` times before each async operation.
+    pub fn max_pending(self, max_pending: u32) -> Self {
+        Self {
+            max_pending,
+            ..self
+        }
+    }
+
+    /// splitmix64
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            self.next_u64() % n
+        }
+    }
+}
+
+impl Iterator for Random {
+    type Item = Fault;
+
+    fn next(&mut self) -> Option<Fault> {
+        let limit = 1 + self.below(self.max_limit.max(1) as u64) as usize;
+        let error = match self.error_one_in {
+            0 => None,
+            n => (self.below(n as u64) == 0).then_some(self.error_kind),
+        };
+        let pending = self.below(self.max_pending as u64 + 1) as u32;
+        Some(Fault {
+            limit,
+            error,
+            pending,
+        })
+    }
+}
+
+/// Error returned by [`Chaotic`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChaoticError<E> {
+    /// Error injected by a [`Fault`].
+    Injected(ErrorKind),
+    /// Error returned by the inner IO object.
+    Other(E),
+}
+
+impl<E> From<E> for ChaoticError<E> {
+    fn from(err: E) -> Self {
+        Self::Other(err)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for ChaoticError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for ChaoticError<E> {}
+
+impl<E: crate::Error> crate::Error for ChaoticError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Injected(kind) => *kind,
+            Self::Other(e) => e.kind(),
+        }
+    }
+}
+
+/// Wrapper injecting faults into every operation on the inner IO object.
+///
+/// See the [module-level documentation](self) for details.
+pub struct Chaotic<T, S> {
+    inner: T,
+    schedule: S,
+}
+
+impl<T, S: Iterator<Item = Fault>> Chaotic<T, S> {
+    /// Create a new wrapper, taking faults from `schedule`.
+    pub fn new(inner: T, schedule: S) -> Self {
+        Self { inner, schedule }
+    }
+
+    /// Consume the wrapper, returning the inner object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Take the next fault, failing if it injects an error.
+    fn fault<E>(&mut self) -> Result<Fault, ChaoticError<E>> {
+        let fault = self.schedule.next().unwrap_or(Fault::NONE);
+        match fault.error {
+            Some(kind) => Err(ChaoticError::Injected(kind)),
+            None => Ok(fault),
+        }
+    }
+}
+
+/// Truncate `len` to the fault's limit, without turning a non-empty transfer into an empty one.
+fn limit(fault: &Fault, len: usize) -> usize {
+    len.min(fault.limit.max(1))
+}
+
+impl<T: crate::Io, S> crate::Io for Chaotic<T, S> {
+    type Error = ChaoticError<T::Error>;
+}
+
+impl<T: crate::blocking::Read, S: Iterator<Item = Fault>> crate::blocking::Read for Chaotic<T, S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let fault = self.fault()?;
+        let n = limit(&fault, buf.len());
+        Ok(self.inner.read(&mut buf[..n])?)
+    }
+}
+
+impl<T: crate::blocking::BufRead, S: Iterator<Item = Fault>> crate::blocking::BufRead
+    for Chaotic<T, S>
+{
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        let fault = self.fault()?;
+        let buf = self.inner.fill_buf()?;
+        Ok(&buf[..limit(&fault, buf.len())])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+impl<T: crate::blocking::Write, S: Iterator<Item = Fault>> crate::blocking::Write
+    for Chaotic<T, S>
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let fault = self.fault()?;
+        let n = limit(&fault, buf.len());
+        Ok(self.inner.write(&buf[..n])?)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.fault()?;
+        Ok(self.inner.flush()?)
+    }
+}
+
+impl<T: crate::blocking::Seek, S: Iterator<Item = Fault>> crate::blocking::Seek for Chaotic<T, S> {
+    fn seek(&mut self, pos: crate::SeekFrom) -> Result<u64, Self::Error> {
+        self.fault()?;
+        Ok(self.inner.seek(pos)?)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::Read, S: Iterator<Item = Fault>> crate::asynch::Read for Chaotic<T, S> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let fault = self.fault()?;
+        crate::asynch::yield_n(fault.pending).await;
+        let n = limit(&fault, buf.len());
+        Ok(self.inner.read(&mut buf[..n]).await?)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::BufRead, S: Iterator<Item = Fault>> crate::asynch::BufRead
+    for Chaotic<T, S>
+{
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        let fault = self.fault()?;
+        crate::asynch::yield_n(fault.pending).await;
+        let buf = self.inner.fill_buf().await?;
+        Ok(&buf[..limit(&fault, buf.len())])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::Write, S: Iterator<Item = Fault>> crate::asynch::Write for Chaotic<T, S> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let fault = self.fault()?;
+        crate::asynch::yield_n(fault.pending).await;
+        let n = limit(&fault, buf.len());
+        Ok(self.inner.write(&buf[..n]).await?)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        let fault = self.fault()?;
+        crate::asynch::yield_n(fault.pending).await;
+        Ok(self.inner.flush().await?)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::Seek, S: Iterator<Item = Fault>> crate::asynch::Seek for Chaotic<T, S> {
+    async fn seek(&mut self, pos: crate::SeekFrom) -> Result<u64, Self::Error> {
+        let fault = self.fault()?;
+        crate::asynch::yield_n(fault.pending).await;
+        Ok(self.inner.seek(pos).await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::{BufRead, Read, Write, WriteAllError};
+
+    #[test]
+    fn short_reads() {
+        let faults = [Fault::limit(2), Fault::limit(0), Fault::NONE];
+        let mut chaos = Chaotic::new(&b"abcdef"[..], faults.into_iter());
+        let mut buf = [0; 8];
+        assert_eq!(chaos.read(&mut buf), Ok(2));
+        // A limit of zero still transfers one byte, rather than signaling EOF.
+        assert_eq!(chaos.read(&mut buf), Ok(1));
+        assert_eq!(chaos.read(&mut buf), Ok(3));
+        assert_eq!(&buf[..3], b"def");
+        assert_eq!(chaos.read(&mut buf), Ok(0));
+    }
+
+    #[test]
+    fn short_fill_buf() {
+        let faults = [Fault::limit(3)];
+        let mut chaos = Chaotic::new(&b"abcdef"[..], faults.into_iter());
+        assert_eq!(chaos.fill_buf(), Ok(&b"abc"[..]));
+        chaos.consume(3);
+        assert_eq!(chaos.fill_buf(), Ok(&b"def"[..]));
+    }
+
+    #[test]
+    fn short_writes() {
+        let mut buf = [0; 8];
+        let faults = [Fault::limit(3), Fault::limit(0)];
+        let mut chaos = Chaotic::new(&mut buf[..], faults.into_iter());
+        assert_eq!(chaos.write(b"abcdef"), Ok(3));
+        assert_eq!(chaos.write(b"def"), Ok(1));
+        chaos.write_all(b"ef").unwrap();
+        assert_eq!(&buf[..6], b"abcdef");
+    }
+
+    #[test]
+    fn injected_errors() {
+        let faults = [Fault::error(ErrorKind::TimedOut), Fault::NONE];
+        let mut chaos = Chaotic::new(&b"ab"[..], faults.into_iter());
+        let mut buf = [0; 2];
+        assert_eq!(
+            chaos.read(&mut buf),
+            Err(ChaoticError::Injected(ErrorKind::TimedOut))
+        );
+        // The failed read consumed nothing.
+        assert_eq!(chaos.read(&mut buf), Ok(2));
+        assert_eq!(&buf, b"ab");
+    }
+
+    #[test]
+    fn short_write_zero() {
+        let mut buf = [0; 1];
+        let mut chaos = Chaotic::new(&mut buf[..], core::iter::empty());
+        assert_eq!(chaos.write_all(b"ab"), Err(WriteAllError::WriteZero));
+    }
+
+    #[test]
+    fn random_is_reproducible() {
+        let a = Random::new(42).errors(ErrorKind::Other, 4).max_pending(3);
+        let b = a.clone();
+        assert!(a.take(100).eq(b.take(100)));
+        assert!(!Random::new(1).take(10).eq(Random::new(2).take(10)));
+    }
+
+    #[test]
+    fn random_respects_settings() {
+        let faults = Random::new(7).max_limit(4).max_pending(2).take(1000);
+        for fault in faults {
+            assert!((1..=4).contains(&fault.limit));
+            assert!(fault.pending <= 2);
+            assert_eq!(fault.error, None);
+        }
+        let errors = Random::new(7)
+            .errors(ErrorKind::TimedOut, 2)
+            .take(1000)
+            .filter(|f| f.error == Some(ErrorKind::TimedOut))
+            .count();
+        assert!((300..700).contains(&errors));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn pending() {
+        use crate::adapters::block_on;
+
+        let faults = [Fault::limit(1).with_pending(3)];
+        let mut chaos = Chaotic::new(&b"ab"[..], faults.into_iter());
+        let mut polls = 0;
+        let mut buf = [0; 2];
+        let res = block_on(crate::asynch::Read::read(&mut chaos, &mut buf), &mut || {
+            polls += 1
+        });
+        assert_eq!(res, Ok(1));
+        assert_eq!(polls, 3);
+        let res = block_on(crate::asynch::Read::read(&mut chaos, &mut buf), &mut || {
+            polls += 1
+        });
+        assert_eq!(res, Ok(1));
+        assert_eq!(polls, 3);
+    }
+}
@@ -4,12 +4,13 @@
 #[cfg(all(feature = "defmt", feature = "log"))]
 compile_error!("You may not enable both `defmt` and `log` features.");
 
+// Tests use the `core` assertions, as the values they compare needn't implement `defmt::Format`.
 macro_rules! assert {
     ($($x:tt)*) => {
         {
-            #[cfg(not(feature = "defmt"))]
+            #[cfg(any(not(feature = "defmt"), test))]
             ::core::assert!($($x)*);
-            #[cfg(feature = "defmt")]
+            #[cfg(all(feature = "defmt", not(test)))]
             ::defmt::assert!($($x)*);
         }
     };
@@ -18,9 +19,9 @@ macro_rules! assert {
 macro_rules! assert_eq {
     ($($x:tt)*) => {
         {
-            #[cfg(not(feature = "defmt"))]
+            #[cfg(any(not(feature = "defmt"), test))]
             ::core::assert_eq!($($x)*);
-            #[cfg(feature = "defmt")]
+            #[cfg(all(feature = "defmt", not(test)))]
             ::defmt::assert_eq!($($x)*);
         }
     };
@@ -29,9 +30,9 @@ macro_rules! assert_eq {
 macro_rules! assert_ne {
     ($($x:tt)*) => {
         {
-            #[cfg(not(feature = "defmt"))]
+            #[cfg(any(not(feature = "defmt"), test))]
             ::core::assert_ne!($($x)*);
-            #[cfg(feature = "defmt")]
+            #[cfg(all(feature = "defmt", not(test)))]
             ::defmt::assert_ne!($($x)*);
         }
     };
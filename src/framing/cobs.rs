@@ -0,0 +1,489 @@
+use super::{Decode, FrameError};
+
+/// Maximum number of data bytes in a COBS block.
+const MAX_BLOCK: usize = 254;
+
+/// Streaming COBS encoder state.
+struct Encoder {
+    /// `block[0]` is reserved for the code byte. It's followed by up to 254 data bytes,
+    /// and room for the frame delimiter.
+    block: [u8; MAX_BLOCK + 2],
+    len: usize,
+    /// The previous block was a full one, which has no implied zero.
+    after_full: bool,
+    /// Data has been written since the last frame delimiter.
+    in_frame: bool,
+}
+
+impl Encoder {
+    const fn new() -> Self {
+        Self {
+            block: [0; MAX_BLOCK + 2],
+            len: 0,
+            after_full: false,
+            in_frame: false,
+        }
+    }
+
+    /// Add a byte to the current block, returning the encoded block once it's complete.
+    fn push(&mut self, byte: u8) -> Option<&[u8]> {
+        self.in_frame = true;
+        if byte != 0 {
+            self.len += 1;
+            self.block[self.len] = byte;
+            if self.len < MAX_BLOCK {
+                return None;
+            }
+        }
+        self.after_full = byte != 0;
+        Some(self.take_block())
+    }
+
+    /// Terminate the frame, returning the encoded tail including the delimiter.
+    fn end_frame(&mut self) -> &[u8] {
+        self.in_frame = false;
+        if self.len == 0 && self.after_full {
+            self.after_full = false;
+            self.block[1] = 0;
+            return &self.block[1..2];
+        }
+        self.after_full = false;
+        self.block[self.len + 1] = 0;
+        let n = self.len + 2;
+        self.block[0] = n as u8 - 1;
+        self.len = 0;
+        &self.block[..n]
+    }
+
+    fn take_block(&mut self) -> &[u8] {
+        let n = self.len + 1;
+        self.block[0] = n as u8;
+        self.len = 0;
+        &self.block[..n]
+    }
+}
+
+/// Streaming writer of COBS-encoded frames, delimited by zero bytes.
+///
+/// Bytes written are encoded on the fly, and sent to the inner writer in blocks of up to 255
+/// bytes. A frame is terminated by [`end_frame`](Self::end_frame), or by `flush` if anything
+/// was written since the previous frame.
+pub struct CobsWriter<W> {
+    inner: W,
+    encoder: Encoder,
+}
+
+impl<W> CobsWriter<W> {
+    /// Create a new COBS writer.
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            encoder: Encoder::new(),
+        }
+    }
+
+    /// Consume the writer, returning the inner object.
+    ///
+    /// Any data written since the last frame delimiter is lost.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: crate::blocking::Write> CobsWriter<W> {
+    /// Terminate the current frame, even if empty.
+    ///
+    /// This does not flush the inner writer.
+    pub fn end_frame(&mut self) -> Result<(), FrameError<W::Error>> {
+        self.inner.write_all(self.encoder.end_frame())?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<W: crate::asynch::Write> CobsWriter<W> {
+    /// Terminate the current frame, even if empty.
+    ///
+    /// This does not flush the inner writer.
+    pub async fn end_frame_async(&mut self) -> Result<(), FrameError<W::Error>> {
+        self.inner.write_all(self.encoder.end_frame()).await?;
+        Ok(())
+    }
+}
+
+impl<W: crate::Io> crate::Io for CobsWriter<W> {
+    type Error = FrameError<W::Error>;
+}
+
+impl<W: crate::blocking::Write> crate::blocking::Write for CobsWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for &byte in buf {
+            if let Some(block) = self.encoder.push(byte) {
+                self.inner.write_all(block)?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.encoder.in_frame {
+            self.end_frame()?;
+        }
+        Ok(self.inner.flush()?)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<W: crate::asynch::Write> crate::asynch::Write for CobsWriter<W> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for &byte in buf {
+            if let Some(block) = self.encoder.push(byte) {
+                self.inner.write_all(block).await?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.encoder.in_frame {
+            self.end_frame_async().await?;
+        }
+        Ok(self.inner.flush().await?)
+    }
+}
+
+/// Streaming COBS decoder state.
+///
+/// This lives in the reader rather than in each `read_frame` call, so that a frame interrupted
+/// by an error from the inner reader can be resumed.
+#[derive(Default)]
+struct Decoder {
+    /// Code byte of the current block, 0 before the first block of a frame.
+    code: u8,
+    /// Data bytes left in the current block.
+    remaining: u8,
+    /// Decoded length so far. May exceed the buffer, for reporting oversized frames.
+    len: usize,
+}
+
+impl Decode for Decoder {
+    /// Empty frames are skipped, so redundant delimiters are harmless.
+    fn feed<E>(&mut self, byte: u8, buf: &mut [u8]) -> Option<Result<usize, FrameError<E>>> {
+        if byte == 0 {
+            let res = if self.code == 0 {
+                None
+            } else if self.remaining != 0 {
+                Some(Err(FrameError::Corrupt))
+            } else if self.len > buf.len() {
+                Some(Err(FrameError::Oversized(self.len)))
+            } else {
+                Some(Ok(self.len))
+            };
+            *self = Self::default();
+            return res;
+        }
+
+        if self.remaining == 0 {
+            if self.code != 0 && self.code != 0xFF {
+                self.put(0, buf);
+            }
+            self.code = byte;
+            self.remaining = byte - 1;
+        } else {
+            self.put(byte, buf);
+            self.remaining -= 1;
+        }
+        None
+    }
+
+    fn in_frame(&self) -> bool {
+        self.code != 0
+    }
+}
+
+impl Decoder {
+    fn put(&mut self, byte: u8, buf: &mut [u8]) {
+        if let Some(b) = buf.get_mut(self.len) {
+            *b = byte;
+        }
+        self.len += 1;
+    }
+}
+
+/// Reader of COBS-encoded frames, delimited by zero bytes.
+///
+/// Frames are decoded into a buffer provided by the caller. Corrupt and oversized frames are
+/// reported as errors, after which the reader resynchronizes on the next delimiter.
+pub struct CobsReader<R> {
+    inner: R,
+    decoder: Decoder,
+}
+
+impl<R> CobsReader<R> {
+    /// Create a new COBS reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            decoder: Decoder::default(),
+        }
+    }
+
+    /// Consume the reader, returning the inner object.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+impl<R: crate::blocking::BufRead> CobsReader<R> {
+    /// Read and decode the next frame into `buf`, returning its length.
+    ///
+    /// Returns `Ok(None)` if the stream ends between frames, and [`FrameError::UnexpectedEof`]
+    /// if it ends in the middle of one. If the inner reader fails mid-frame, calling this again
+    /// with the same buffer resumes decoding where it left off.
+    pub fn read_frame(&mut self, buf: &mut [u8]) -> Result<Option<usize>, FrameError<R::Error>> {
+        super::read_frame(&mut self.inner, &mut self.decoder, buf)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<R: crate::asynch::BufRead> CobsReader<R> {
+    /// Read and decode the next frame into `buf`, returning its length.
+    ///
+    /// See [`read_frame`](Self::read_frame) for details.
+    pub async fn read_frame_async(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<usize>, FrameError<R::Error>> {
+        super::read_frame_async(&mut self.inner, &mut self.decoder, buf).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::Write;
+    use crate::chaos::{Chaotic, ChaoticError, Fault};
+    use crate::ErrorKind;
+
+    fn encode(frame: &[u8], out: &mut [u8]) -> usize {
+        let total = out.len();
+        let mut w = CobsWriter::new(&mut *out);
+        w.write_all(frame).unwrap();
+        w.end_frame().unwrap();
+        total - w.into_inner().len()
+    }
+
+    fn check(frame: &[u8], encoded: &[u8]) {
+        let mut out = [0; 600];
+        let n = encode(frame, &mut out);
+        assert_eq!(&out[..n], encoded);
+
+        let mut r = CobsReader::new(encoded);
+        let mut buf = [0; 600];
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(frame.len())));
+        assert_eq!(&buf[..frame.len()], frame);
+        assert_eq!(r.read_frame(&mut buf), Ok(None));
+    }
+
+    /// `start`, `start + 1`, ... wrapping, `len` bytes.
+    fn seq(start: u8, len: usize, out: &mut [u8]) -> &[u8] {
+        for (i, b) in out[..len].iter_mut().enumerate() {
+            *b = start.wrapping_add(i as u8);
+        }
+        &out[..len]
+    }
+
+    #[test]
+    fn vectors() {
+        check(&[], &[0x01, 0x00]);
+        check(&[0x00], &[0x01, 0x01, 0x00]);
+        check(&[0x00, 0x00], &[0x01, 0x01, 0x01, 0x00]);
+        check(
+            &[0x11, 0x22, 0x00, 0x33],
+            &[0x03, 0x11, 0x22, 0x02, 0x33, 0x00],
+        );
+        check(
+            &[0x11, 0x22, 0x33, 0x44],
+            &[0x05, 0x11, 0x22, 0x33, 0x44, 0x00],
+        );
+        check(
+            &[0x11, 0x00, 0x00, 0x00],
+            &[0x02, 0x11, 0x01, 0x01, 0x01, 0x00],
+        );
+        check(&[0xFF, 0x00, 0xFF], &[0x02, 0xFF, 0x02, 0xFF, 0x00]);
+    }
+
+    #[test]
+    fn long_runs() {
+        let (mut frame, mut encoded) = ([0; 300], [0; 300]);
+
+        // 254 non-zero bytes fill a block, which has no implied zero.
+        let f = seq(0x01, 254, &mut frame);
+        encoded[0] = 0xFF;
+        encoded[1..255].copy_from_slice(f);
+        encoded[255] = 0x00;
+        check(f, &encoded[..256]);
+
+        // A zero followed by 254 non-zero bytes.
+        let f = seq(0x00, 255, &mut frame);
+        encoded[..2].copy_from_slice(&[0x01, 0xFF]);
+        encoded[2..256].copy_from_slice(&f[1..]);
+        encoded[256] = 0x00;
+        check(f, &encoded[..257]);
+
+        // 255 non-zero bytes need a second block.
+        let f = seq(0x01, 255, &mut frame);
+        encoded[0] = 0xFF;
+        encoded[1..255].copy_from_slice(&f[..254]);
+        encoded[255..258].copy_from_slice(&[0x02, 0xFF, 0x00]);
+        check(f, &encoded[..258]);
+
+        // A full block followed by a zero.
+        let f = seq(0x02, 255, &mut frame);
+        encoded[0] = 0xFF;
+        encoded[1..255].copy_from_slice(&f[..254]);
+        encoded[255..258].copy_from_slice(&[0x01, 0x01, 0x00]);
+        check(f, &encoded[..258]);
+    }
+
+    #[test]
+    fn round_trip_lengths() {
+        let mut frame = [0; 600];
+        let mut out = [0; 700];
+        let mut buf = [0; 600];
+        for len in (0..600).step_by(7).chain([253, 254, 255, 508, 509]) {
+            // Mostly non-zero, with zeros every 300 bytes.
+            for (i, b) in frame[..len].iter_mut().enumerate() {
+                *b = if i % 300 == 299 {
+                    0
+                } else {
+                    (i % 255) as u8 + 1
+                };
+            }
+            let n = encode(&frame[..len], &mut out);
+            assert!(!out[..n - 1].contains(&0));
+            let mut r = CobsReader::new(&out[..n]);
+            assert_eq!(r.read_frame(&mut buf), Ok(Some(len)));
+            assert_eq!(&buf[..len], &frame[..len]);
+        }
+    }
+
+    #[test]
+    fn flush_ends_frame() {
+        let mut out = [0; 16];
+        let mut w = CobsWriter::new(&mut out[..]);
+        w.flush().unwrap();
+        w.write_all(b"ab").unwrap();
+        w.flush().unwrap();
+        w.flush().unwrap();
+        assert_eq!(w.into_inner().len(), 12);
+        assert_eq!(&out[..4], &[0x03, b'a', b'b', 0x00]);
+    }
+
+    #[test]
+    fn malformed() {
+        let mut buf = [0; 4];
+
+        // The second block is cut short by a delimiter, then decoding resumes.
+        let mut r = CobsReader::new(&[0x02, 0x11, 0x05, 0x22, 0x00, 0x02, 0x33, 0x00][..]);
+        assert_eq!(r.read_frame(&mut buf), Err(FrameError::Corrupt));
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(1)));
+        assert_eq!(buf[0], 0x33);
+
+        // Too large for the buffer, then decoding resumes.
+        let mut r = CobsReader::new(&[0x06, 1, 2, 3, 4, 5, 0x00, 0x02, 0x33, 0x00][..]);
+        assert_eq!(r.read_frame(&mut buf), Err(FrameError::Oversized(5)));
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(1)));
+        assert_eq!(buf[0], 0x33);
+    }
+
+    #[test]
+    fn delimiters_between_frames() {
+        let mut buf = [0; 4];
+        let mut r = CobsReader::new(&[0x00, 0x00, 0x02, 0x11, 0x00, 0x00][..]);
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(1)));
+        assert_eq!(r.read_frame(&mut buf), Ok(None));
+    }
+
+    #[test]
+    fn eof() {
+        let mut buf = [0; 4];
+        assert_eq!(CobsReader::new(&[][..]).read_frame(&mut buf), Ok(None));
+
+        let mut r = CobsReader::new(&[0x03, 0x11][..]);
+        assert_eq!(r.read_frame(&mut buf), Err(FrameError::UnexpectedEof));
+        assert_eq!(r.read_frame(&mut buf), Ok(None));
+    }
+
+    #[test]
+    fn resume_after_error() {
+        let faults = [
+            Fault::limit(2),
+            Fault::error(ErrorKind::TimedOut),
+            Fault::limit(1),
+        ];
+        let data = &[0x03, 0x11, 0x22, 0x02, 0x33, 0x00][..];
+        let mut r = CobsReader::new(Chaotic::new(data, faults.into_iter()));
+        let mut buf = [0; 4];
+        assert_eq!(
+            r.read_frame(&mut buf),
+            Err(FrameError::Other(ChaoticError::Injected(
+                ErrorKind::TimedOut
+            )))
+        );
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(4)));
+        assert_eq!(&buf, &[0x11, 0x22, 0x00, 0x33]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+
+        let mut out = [0; 16];
+        let mut w = CobsWriter::new(&mut out[..]);
+        block_on(
+            async {
+                crate::asynch::Write::write_all(&mut w, &[0x11, 0x00])
+                    .await
+                    .unwrap();
+                crate::asynch::Write::flush(&mut w).await.unwrap();
+            },
+            &mut Spin,
+        );
+        assert_eq!(w.into_inner().len(), 12);
+        assert_eq!(&out[..4], &[0x02, 0x11, 0x01, 0x00]);
+
+        let mut r = CobsReader::new(&out[..4]);
+        let mut buf = [0; 4];
+        assert_eq!(
+            block_on(r.read_frame_async(&mut buf), &mut Spin),
+            Ok(Some(2))
+        );
+        assert_eq!(block_on(r.read_frame_async(&mut buf), &mut Spin), Ok(None));
+        assert_eq!(&buf[..2], &[0x11, 0x00]);
+    }
+}
@@ -0,0 +1,518 @@
+use super::FrameError;
+
+const FLAG: u8 = 0x7E;
+const ESC: u8 = 0x7D;
+const ESC_XOR: u8 = 0x20;
+
+/// Size of the receive buffer.
+const RX_BUF: usize = 32;
+
+/// Frame check sequence appended to [`Hdlc`] frames.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Fcs {
+    /// 16-bit FCS, as used by default by PPP.
+    Fcs16,
+    /// 32-bit FCS.
+    Fcs32,
+}
+
+impl Fcs {
+    const fn len(self) -> usize {
+        match self {
+            Fcs::Fcs16 => 2,
+            Fcs::Fcs32 => 4,
+        }
+    }
+}
+
+/// Running FCS computation, as specified in RFC 1662 appendix C.
+#[derive(Copy, Clone)]
+struct Crc {
+    kind: Fcs,
+    value: u32,
+}
+
+impl Crc {
+    const fn new(kind: Fcs) -> Self {
+        let value = match kind {
+            Fcs::Fcs16 => 0xFFFF,
+            Fcs::Fcs32 => 0xFFFF_FFFF,
+        };
+        Self { kind, value }
+    }
+
+    fn update(&mut self, byte: u8) {
+        let poly = match self.kind {
+            Fcs::Fcs16 => 0x8408,
+            Fcs::Fcs32 => 0xEDB8_8320,
+        };
+        self.value ^= byte as u32;
+        for _ in 0..8 {
+            self.value = if self.value & 1 != 0 {
+                (self.value >> 1) ^ poly
+            } else {
+                self.value >> 1
+            };
+        }
+    }
+
+    /// Whether the FCS computed over a frame including its FCS field is correct.
+    fn is_good(&self) -> bool {
+        match self.kind {
+            Fcs::Fcs16 => self.value == 0xF0B8,
+            Fcs::Fcs32 => self.value == 0xDEBB_20E3,
+        }
+    }
+
+    /// The FCS field to transmit, least significant byte first.
+    fn field(&self) -> ([u8; 4], usize) {
+        let fcs = !self.value;
+        (fcs.to_le_bytes(), self.kind.len())
+    }
+}
+
+/// Escape sequence for `byte`, if it needs one given the ACCM.
+fn escape(byte: u8, accm: u32) -> Option<u8> {
+    let control = byte < 0x20 && accm & (1 << byte) != 0;
+    (byte == FLAG || byte == ESC || control).then_some(byte ^ ESC_XOR)
+}
+
+/// Streaming HDLC-like decoder state.
+struct Decoder {
+    crc: Crc,
+    /// The previous byte was the escape byte.
+    escape: bool,
+    /// Bytes received in the current frame, including the FCS field.
+    len: usize,
+    /// Last bytes received, which are the FCS field if the frame ends now. They're only
+    /// moved to the caller's buffer once it's known they're not.
+    tail: [u8; 4],
+    /// Number of frames discarded.
+    discarded: u32,
+}
+
+impl Decoder {
+    const fn new(fcs: Fcs) -> Self {
+        Self {
+            crc: Crc::new(fcs),
+            escape: false,
+            len: 0,
+            tail: [0; 4],
+            discarded: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.crc = Crc::new(self.crc.kind);
+        self.escape = false;
+        self.len = 0;
+    }
+
+    /// Handle EOF from the inner reader, which is only an error in the middle of a frame.
+    fn eof<E>(&mut self) -> Result<Option<usize>, FrameError<E>> {
+        let in_frame = self.len != 0 || self.escape;
+        self.reset();
+        if in_frame {
+            Err(FrameError::UnexpectedEof)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Feed a byte, returning the payload length once a valid frame is complete.
+    ///
+    /// Empty frames are skipped, and malformed frames are counted and discarded.
+    fn feed(&mut self, byte: u8, rx_accm: u32, buf: &mut [u8]) -> Option<usize> {
+        if byte == FLAG {
+            let fcs_len = self.crc.kind.len();
+            let res = if self.len == 0 && !self.escape {
+                None
+            } else if self.escape
+                || self.len < fcs_len
+                || !self.crc.is_good()
+                || self.len - fcs_len > buf.len()
+            {
+                // `ESC FLAG` is an abort sequence.
+                self.discarded = self.discarded.wrapping_add(1);
+                None
+            } else {
+                Some(self.len - fcs_len)
+            };
+            self.reset();
+            return res;
+        }
+
+        // Control characters in the ACCM were inserted by the link, not sent by the peer.
+        if byte < 0x20 && rx_accm & (1 << byte) != 0 {
+            return None;
+        }
+
+        let byte = if self.escape {
+            self.escape = false;
+            byte ^ ESC_XOR
+        } else if byte == ESC {
+            self.escape = true;
+            return None;
+        } else {
+            byte
+        };
+
+        self.crc.update(byte);
+        let fcs_len = self.crc.kind.len();
+        if self.len >= fcs_len {
+            if let Some(b) = buf.get_mut(self.len - fcs_len) {
+                *b = self.tail[0];
+            }
+        }
+        self.tail.copy_within(1..fcs_len, 0);
+        self.tail[fcs_len - 1] = byte;
+        self.len += 1;
+        None
+    }
+}
+
+/// HDLC-like framer, as used by PPP and specified in RFC 1662.
+///
+/// Frames are delimited by `0x7E` flags, with `0x7D` escaping, and carry a 16 or 32-bit frame
+/// check sequence. Control characters set in the transmit ACCM (async control character map)
+/// are escaped when sending, and control characters set in the receive ACCM are dropped when
+/// receiving. Both default to all ones.
+///
+/// Received frames with a bad FCS, aborted frames, and frames that don't fit in the caller's
+/// buffer are silently discarded, and counted in [`discarded`](Self::discarded).
+pub struct Hdlc<T> {
+    inner: T,
+    tx_accm: u32,
+    rx_accm: u32,
+    decoder: Decoder,
+    rx: [u8; RX_BUF],
+    rx_pos: usize,
+    rx_len: usize,
+}
+
+impl<T> Hdlc<T> {
+    /// Create a new framer.
+    pub const fn new(inner: T, fcs: Fcs) -> Self {
+        Self {
+            inner,
+            tx_accm: 0xFFFF_FFFF,
+            rx_accm: 0xFFFF_FFFF,
+            decoder: Decoder::new(fcs),
+            rx: [0; RX_BUF],
+            rx_pos: 0,
+            rx_len: 0,
+        }
+    }
+
+    /// Set the ACCM used when sending.
+    ///
+    /// Bit `n` set means character `n` is escaped.
+    pub fn set_tx_accm(&mut self, accm: u32) {
+        self.tx_accm = accm;
+    }
+
+    /// Set the ACCM used when receiving.
+    ///
+    /// Bit `n` set means unescaped character `n` is dropped.
+    pub fn set_rx_accm(&mut self, accm: u32) {
+        self.rx_accm = accm;
+    }
+
+    /// Number of received frames discarded because they were malformed or too large.
+    pub fn discarded(&self) -> u32 {
+        self.decoder.discarded
+    }
+
+    /// Consume the framer, returning the inner object.
+    ///
+    /// Any received data not yet decoded is lost.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// FCS field to send after `frame`.
+    fn fcs(&self, frame: &[u8]) -> ([u8; 4], usize) {
+        let mut crc = Crc::new(self.decoder.crc.kind);
+        for &byte in frame {
+            crc.update(byte);
+        }
+        crc.field()
+    }
+
+    /// Decode buffered received bytes, returning the payload length once a frame is complete.
+    fn decode(&mut self, buf: &mut [u8]) -> Option<usize> {
+        while self.rx_pos < self.rx_len {
+            let byte = self.rx[self.rx_pos];
+            self.rx_pos += 1;
+            if let Some(n) = self.decoder.feed(byte, self.rx_accm, buf) {
+                return Some(n);
+            }
+        }
+        None
+    }
+}
+
+impl<T: crate::blocking::Read> Hdlc<T> {
+    /// Read the next valid frame into `buf`, returning its payload length.
+    ///
+    /// The FCS is checked and stripped. Returns `Ok(None)` if the stream ends between frames,
+    /// and [`FrameError::UnexpectedEof`] if it ends in the middle of one.
+    pub fn read_frame(&mut self, buf: &mut [u8]) -> Result<Option<usize>, FrameError<T::Error>> {
+        loop {
+            if let Some(n) = self.decode(buf) {
+                return Ok(Some(n));
+            }
+            let n = self.inner.read(&mut self.rx)?;
+            if n == 0 {
+                return self.decoder.eof();
+            }
+            self.rx_pos = 0;
+            self.rx_len = n;
+        }
+    }
+}
+
+impl<T: crate::blocking::Write> Hdlc<T> {
+    /// Write `frame`, followed by its FCS.
+    ///
+    /// This does not flush the inner writer.
+    pub fn write_frame(&mut self, frame: &[u8]) -> Result<(), FrameError<T::Error>> {
+        let accm = self.tx_accm;
+        let (fcs, fcs_len) = self.fcs(frame);
+        self.inner.write_all(&[FLAG])?;
+        for data in [frame, &fcs[..fcs_len]] {
+            super::write_escaped(&mut self.inner, data, ESC, |b| escape(b, accm))?;
+        }
+        self.inner.write_all(&[FLAG])?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::Read> Hdlc<T> {
+    /// Read the next valid frame into `buf`, returning its payload length.
+    ///
+    /// See [`read_frame`](Self::read_frame) for details.
+    pub async fn read_frame_async(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<usize>, FrameError<T::Error>> {
+        loop {
+            if let Some(n) = self.decode(buf) {
+                return Ok(Some(n));
+            }
+            let n = self.inner.read(&mut self.rx).await?;
+            if n == 0 {
+                return self.decoder.eof();
+            }
+            self.rx_pos = 0;
+            self.rx_len = n;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::Write> Hdlc<T> {
+    /// Write `frame`, followed by its FCS.
+    ///
+    /// This does not flush the inner writer.
+    pub async fn write_frame_async(&mut self, frame: &[u8]) -> Result<(), FrameError<T::Error>> {
+        let accm = self.tx_accm;
+        let (fcs, fcs_len) = self.fcs(frame);
+        self.inner.write_all(&[FLAG]).await?;
+        for data in [frame, &fcs[..fcs_len]] {
+            super::write_escaped_async(&mut self.inner, data, ESC, |b| escape(b, accm)).await?;
+        }
+        self.inner.write_all(&[FLAG]).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(frame: &[u8], fcs: Fcs, tx_accm: u32, out: &mut [u8]) -> usize {
+        let total = out.len();
+        let mut hdlc = Hdlc::new(&mut *out, fcs);
+        hdlc.set_tx_accm(tx_accm);
+        hdlc.write_frame(frame).unwrap();
+        total - hdlc.into_inner().len()
+    }
+
+    #[test]
+    fn fcs16() {
+        let mut out = [0; 32];
+        let n = encode(b"123456789", Fcs::Fcs16, 0, &mut out);
+        assert_eq!(&out[..n], b"\x7e123456789\x6e\x90\x7e");
+
+        let mut hdlc = Hdlc::new(&out[..n], Fcs::Fcs16);
+        let mut buf = [0; 16];
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(Some(9)));
+        assert_eq!(&buf[..9], b"123456789");
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(None));
+        assert_eq!(hdlc.discarded(), 0);
+    }
+
+    #[test]
+    fn fcs32() {
+        let mut out = [0; 32];
+        let n = encode(b"123456789", Fcs::Fcs32, 0, &mut out);
+        assert_eq!(&out[..n], b"\x7e123456789\x26\x39\xf4\xcb\x7e");
+
+        let mut hdlc = Hdlc::new(&out[..n], Fcs::Fcs32);
+        let mut buf = [0; 16];
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(Some(9)));
+        assert_eq!(&buf[..9], b"123456789");
+        assert_eq!(hdlc.discarded(), 0);
+    }
+
+    #[test]
+    fn wrong_fcs_width() {
+        let mut out = [0; 32];
+        let n = encode(b"123456789", Fcs::Fcs16, 0, &mut out);
+        let mut hdlc = Hdlc::new(&out[..n], Fcs::Fcs32);
+        assert_eq!(hdlc.read_frame(&mut [0; 16]), Ok(None));
+        assert_eq!(hdlc.discarded(), 1);
+    }
+
+    #[test]
+    fn accm() {
+        let frame = [0x01, FLAG, 0x11, ESC, 0x20];
+        let mut out = [0; 32];
+
+        // By default, all control characters are escaped.
+        let n = encode(&frame, Fcs::Fcs16, 0xFFFF_FFFF, &mut out);
+        assert_eq!(&out[1..9], &[ESC, 0x21, ESC, 0x5E, ESC, 0x31, ESC, 0x5D]);
+        assert_eq!(out[9], 0x20);
+        let mut buf = [0; 8];
+        let mut hdlc = Hdlc::new(&out[..n], Fcs::Fcs16);
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(Some(5)));
+        assert_eq!(&buf[..5], &frame);
+
+        // Only the control characters in the ACCM are escaped.
+        let n = encode(&frame, Fcs::Fcs16, 1 << 0x11, &mut out);
+        assert_eq!(&out[1..8], &[0x01, ESC, 0x5E, ESC, 0x31, ESC, 0x5D]);
+        let mut hdlc = Hdlc::new(&out[..n], Fcs::Fcs16);
+        hdlc.set_rx_accm(0);
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(Some(5)));
+        assert_eq!(&buf[..5], &frame);
+    }
+
+    #[test]
+    fn rx_accm_drops_control_characters() {
+        let mut out = [0; 32];
+        let n = encode(b"abc", Fcs::Fcs16, 0, &mut out);
+        // XON/XOFF inserted by the link.
+        let mut noisy = [0; 34];
+        noisy[..2].copy_from_slice(&out[..2]);
+        noisy[2] = 0x11;
+        noisy[3..n].copy_from_slice(&out[2..n - 1]);
+        noisy[n] = 0x13;
+        noisy[n + 1] = FLAG;
+        let mut buf = [0; 8];
+        let mut hdlc = Hdlc::new(&noisy[..n + 2], Fcs::Fcs16);
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(Some(3)));
+        assert_eq!(&buf[..3], b"abc");
+
+        // With an empty receive ACCM they're data, and corrupt the frame.
+        let mut hdlc = Hdlc::new(&noisy[..n + 2], Fcs::Fcs16);
+        hdlc.set_rx_accm(0);
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(None));
+        assert_eq!(hdlc.discarded(), 1);
+    }
+
+    #[test]
+    fn malformed_frames_are_discarded() {
+        let mut good = [0; 16];
+        let n = encode(b"ok", Fcs::Fcs16, 0xFFFF_FFFF, &mut good);
+
+        let mut data = [0; 64];
+        let mut len = 0;
+        let mut push = |bytes: &[u8]| {
+            data[len..][..bytes.len()].copy_from_slice(bytes);
+            len += bytes.len();
+        };
+        // Bad FCS.
+        push(&[FLAG, b'o', b'k', b'x', b'y', FLAG]);
+        // Aborted.
+        push(&[b'o', b'k', ESC, FLAG]);
+        // Too short for an FCS.
+        push(&[b'z', FLAG]);
+        // Too large for the buffer.
+        let mut large = [0; 16];
+        let large_len = encode(b"ok!", Fcs::Fcs16, 0xFFFF_FFFF, &mut large);
+        push(&large[..large_len]);
+        push(&good[..n]);
+
+        let mut hdlc = Hdlc::new(&data[..len], Fcs::Fcs16);
+        let mut buf = [0; 2];
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(Some(2)));
+        assert_eq!(&buf, b"ok");
+        assert_eq!(hdlc.discarded(), 4);
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(None));
+    }
+
+    #[test]
+    fn eof() {
+        let mut buf = [0; 8];
+        let mut hdlc = Hdlc::new(&[FLAG, FLAG][..], Fcs::Fcs16);
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(None));
+
+        let mut hdlc = Hdlc::new(&[FLAG, b'a', b'b'][..], Fcs::Fcs16);
+        assert_eq!(hdlc.read_frame(&mut buf), Err(FrameError::UnexpectedEof));
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(None));
+    }
+
+    #[test]
+    fn long_frame() {
+        // Escaped data spanning several chunks.
+        let mut frame = [0; 100];
+        for (i, b) in frame.iter_mut().enumerate() {
+            *b = if i % 2 == 0 { FLAG } else { i as u8 };
+        }
+        let mut out = [0; 256];
+        let n = encode(&frame, Fcs::Fcs32, 0xFFFF_FFFF, &mut out);
+        let mut hdlc = Hdlc::new(&out[..n], Fcs::Fcs32);
+        let mut buf = [0; 100];
+        assert_eq!(hdlc.read_frame(&mut buf), Ok(Some(100)));
+        assert_eq!(buf, frame);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+
+        let mut out = [0; 32];
+        let mut hdlc = Hdlc::new(&mut out[..], Fcs::Fcs16);
+        hdlc.set_tx_accm(0);
+        block_on(hdlc.write_frame_async(b"123456789"), &mut Spin).unwrap();
+        assert_eq!(hdlc.into_inner().len(), 19);
+        assert_eq!(&out[..13], b"\x7e123456789\x6e\x90\x7e");
+
+        let mut hdlc = Hdlc::new(&out[..13], Fcs::Fcs16);
+        let mut buf = [0; 16];
+        assert_eq!(
+            block_on(hdlc.read_frame_async(&mut buf), &mut Spin),
+            Ok(Some(9))
+        );
+        assert_eq!(
+            block_on(hdlc.read_frame_async(&mut buf), &mut Spin),
+            Ok(None)
+        );
+    }
+}
@@ -0,0 +1,546 @@
+use super::FrameError;
+use crate::blocking::ReadVarint;
+use crate::varint::{self, ReadVarintError};
+
+/// Size of the buffer the prefix and the start of the frame are written from.
+const HEAD: usize = 64;
+
+/// Width of the length prefix of a [`LengthDelimited`] frame.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PrefixWidth {
+    /// 1 byte, frames up to 255 bytes.
+    U8,
+    /// 2 bytes, frames up to 65535 bytes.
+    U16,
+    /// 4 bytes.
+    U32,
+    /// Unsigned LEB128 [varint](crate::varint), 1 to 10 bytes. Endianness does not apply.
+    Varint,
+}
+
+/// Byte order of a multi-byte length prefix.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Endian {
+    /// Most significant byte first, also known as network byte order.
+    Big,
+    /// Least significant byte first.
+    Little,
+}
+
+/// Codec for frames preceded by their length.
+///
+/// The codec itself is just configuration: it holds no state, and is passed the IO object on
+/// each call. By default, it uses a big-endian `u32` prefix and a maximum frame length of
+/// 65535 bytes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LengthDelimited {
+    width: PrefixWidth,
+    endian: Endian,
+    max_frame_len: usize,
+}
+
+impl Default for LengthDelimited {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LengthDelimited {
+    /// Create a new codec with the default configuration.
+    pub const fn new() -> Self {
+        Self {
+            width: PrefixWidth::U32,
+            endian: Endian::Big,
+            max_frame_len: 65535,
+        }
+    }
+
+    /// Set the width of the length prefix.
+    pub const fn prefix_width(self, width: PrefixWidth) -> Self {
+        Self { width, ..self }
+    }
+
+    /// Set the byte order of the length prefix.
+    pub const fn endian(self, endian: Endian) -> Self {
+        Self { endian, ..self }
+    }
+
+    /// Set the maximum frame length, excluding the prefix.
+    pub const fn max_frame_len(self, max_frame_len: usize) -> Self {
+        Self {
+            max_frame_len,
+            ..self
+        }
+    }
+
+    /// Largest frame length allowed by both the configuration and the prefix width.
+    fn max_len(&self) -> usize {
+        let prefix_max = match self.width {
+            PrefixWidth::U8 => u8::MAX as u64,
+            PrefixWidth::U16 => u16::MAX as u64,
+            PrefixWidth::U32 => u32::MAX as u64,
+            PrefixWidth::Varint => u64::MAX,
+        };
+        self.max_frame_len
+            .min(prefix_max.try_into().unwrap_or(usize::MAX))
+    }
+
+    fn fixed_len(&self) -> usize {
+        match self.width {
+            PrefixWidth::U8 => 1,
+            PrefixWidth::U16 => 2,
+            PrefixWidth::U32 => 4,
+            PrefixWidth::Varint => 0,
+        }
+    }
+
+    /// Encode the prefix for a frame of `len` bytes, returning its length within `out`.
+    fn encode_prefix<E>(
+        &self,
+        len: usize,
+        out: &mut [u8; varint::MAX_LEN],
+    ) -> Result<usize, FrameError<E>> {
+        if len > self.max_len() {
+            return Err(FrameError::Oversized(len));
+        }
+        let mut v = len as u64;
+        if self.width == PrefixWidth::Varint {
+            return Ok(varint::encode(v, out));
+        }
+        let n = self.fixed_len();
+        for i in 0..n {
+            let pos = match self.endian {
+                Endian::Big => n - 1 - i,
+                Endian::Little => i,
+            };
+            out[pos] = v as u8;
+            v >>= 8;
+        }
+        Ok(n)
+    }
+
+    /// Encode the prefix followed by the start of `frame` into `head`, so that they're written
+    /// together. Returns the encoded part, and the rest of the frame.
+    fn encode_head<'a, 'b, E>(
+        &self,
+        frame: &'b [u8],
+        head: &'a mut [u8; HEAD],
+    ) -> Result<(&'a [u8], &'b [u8]), FrameError<E>> {
+        let mut prefix = [0; varint::MAX_LEN];
+        let n = self.encode_prefix(frame.len(), &mut prefix)?;
+        let (data, rest) = frame.split_at(frame.len().min(HEAD - n));
+        head[..n].copy_from_slice(&prefix[..n]);
+        head[n..][..data.len()].copy_from_slice(data);
+        Ok((&head[..n + data.len()], rest))
+    }
+
+    fn decode_fixed(&self, bytes: &[u8]) -> u64 {
+        let mut v = 0;
+        for i in 0..bytes.len() {
+            let b = match self.endian {
+                Endian::Big => bytes[i],
+                Endian::Little => bytes[bytes.len() - 1 - i],
+            };
+            v = (v << 8) | b as u64;
+        }
+        v
+    }
+
+    /// Check a decoded frame length against the limits.
+    fn check_len<E>(&self, len: u64, buf_len: usize) -> Result<usize, FrameError<E>> {
+        let len = usize::try_from(len).unwrap_or(usize::MAX);
+        if len > self.max_len() || len > buf_len {
+            Err(FrameError::Oversized(len))
+        } else {
+            Ok(len)
+        }
+    }
+
+    /// Read a frame into `buf`, returning its length.
+    ///
+    /// If the frame is within the maximum frame length but does not fit in `buf`, it is
+    /// discarded and [`FrameError::Oversized`] is returned, so that the next call reads the
+    /// next frame. If it exceeds the maximum frame length, the stream is most likely corrupt,
+    /// and is left positioned just after the prefix.
+    pub fn read_frame<R: crate::blocking::Read + ?Sized>(
+        &self,
+        reader: &mut R,
+        buf: &mut [u8],
+    ) -> Result<usize, FrameError<R::Error>> {
+        let len = self.read_prefix(reader)?;
+        match self.check_len(len, buf.len()) {
+            Ok(len) => {
+                reader.read_exact(&mut buf[..len])?;
+                Ok(len)
+            }
+            Err(FrameError::Oversized(len)) if len <= self.max_len() => {
+                let mut scratch = [0; 32];
+                let mut left = len;
+                while left > 0 {
+                    let n = left.min(scratch.len());
+                    reader.read_exact(&mut scratch[..n])?;
+                    left -= n;
+                }
+                Err(FrameError::Oversized(len))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read a frame into a newly allocated `Vec`.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "std", feature = "alloc"))))]
+    pub fn read_frame_to_vec<R: crate::blocking::Read + ?Sized>(
+        &self,
+        reader: &mut R,
+    ) -> Result<alloc::vec::Vec<u8>, FrameError<R::Error>> {
+        let len = self.read_prefix(reader)?;
+        let len = self.check_len(len, usize::MAX)?;
+        let mut buf = alloc::vec![0; len];
+        reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Write `frame`, preceded by its length.
+    ///
+    /// The prefix is written together with the start of the frame, so that it's never left
+    /// dangling on its own if the writer fails. Frames of up to 64 bytes including the prefix
+    /// are written with a single `write_all`, and longer ones with two.
+    ///
+    /// The write is not atomic: `write_all` may take several writes, so if the writer fails,
+    /// part of the frame may have been written. The stream is then out of sync, and the peer
+    /// will misread what follows.
+    pub fn write_frame<W: crate::blocking::Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        frame: &[u8],
+    ) -> Result<(), FrameError<W::Error>> {
+        let mut head = [0; HEAD];
+        let (head, rest) = self.encode_head(frame, &mut head)?;
+        writer.write_all(head)?;
+        if !rest.is_empty() {
+            writer.write_all(rest)?;
+        }
+        Ok(())
+    }
+
+    fn read_prefix<R: crate::blocking::Read + ?Sized>(
+        &self,
+        reader: &mut R,
+    ) -> Result<u64, FrameError<R::Error>> {
+        if self.width == PrefixWidth::Varint {
+            return match reader.read_varint() {
+                Ok(v) => Ok(v),
+                Err(ReadVarintError::InvalidData) => Err(FrameError::Corrupt),
+                Err(ReadVarintError::UnexpectedEof) => Err(FrameError::UnexpectedEof),
+                Err(ReadVarintError::Other(e)) => Err(FrameError::Other(e)),
+            };
+        }
+        let mut prefix = [0; 4];
+        let prefix = &mut prefix[..self.fixed_len()];
+        reader.read_exact(prefix)?;
+        Ok(self.decode_fixed(prefix))
+    }
+
+    /// Read a frame into `buf`, returning its length.
+    ///
+    /// See [`read_frame`](Self::read_frame) for details.
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn read_frame_async<R: crate::asynch::Read + ?Sized>(
+        &self,
+        reader: &mut R,
+        buf: &mut [u8],
+    ) -> Result<usize, FrameError<R::Error>> {
+        let len = self.read_prefix_async(reader).await?;
+        match self.check_len(len, buf.len()) {
+            Ok(len) => {
+                reader.read_exact(&mut buf[..len]).await?;
+                Ok(len)
+            }
+            Err(FrameError::Oversized(len)) if len <= self.max_len() => {
+                let mut scratch = [0; 32];
+                let mut left = len;
+                while left > 0 {
+                    let n = left.min(scratch.len());
+                    reader.read_exact(&mut scratch[..n]).await?;
+                    left -= n;
+                }
+                Err(FrameError::Oversized(len))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read a frame into a newly allocated `Vec`.
+    #[cfg(all(feature = "async", feature = "alloc"))]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(all(feature = "async", any(feature = "std", feature = "alloc"))))
+    )]
+    pub async fn read_frame_to_vec_async<R: crate::asynch::Read + ?Sized>(
+        &self,
+        reader: &mut R,
+    ) -> Result<alloc::vec::Vec<u8>, FrameError<R::Error>> {
+        let len = self.read_prefix_async(reader).await?;
+        let len = self.check_len(len, usize::MAX)?;
+        let mut buf = alloc::vec![0; len];
+        reader.read_exact(&mut buf).await?;
+        Ok(buf)
+    }
+
+    /// Write `frame`, preceded by its length.
+    ///
+    /// See [`write_frame`](Self::write_frame) for details.
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn write_frame_async<W: crate::asynch::Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        frame: &[u8],
+    ) -> Result<(), FrameError<W::Error>> {
+        let mut head = [0; HEAD];
+        let (head, rest) = self.encode_head(frame, &mut head)?;
+        writer.write_all(head).await?;
+        if !rest.is_empty() {
+            writer.write_all(rest).await?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    async fn read_prefix_async<R: crate::asynch::Read + ?Sized>(
+        &self,
+        reader: &mut R,
+    ) -> Result<u64, FrameError<R::Error>> {
+        if self.width == PrefixWidth::Varint {
+            return match crate::asynch::ReadVarint::read_varint(reader).await {
+                Ok(v) => Ok(v),
+                Err(ReadVarintError::InvalidData) => Err(FrameError::Corrupt),
+                Err(ReadVarintError::UnexpectedEof) => Err(FrameError::UnexpectedEof),
+                Err(ReadVarintError::Other(e)) => Err(FrameError::Other(e)),
+            };
+        }
+        let mut prefix = [0; 4];
+        let prefix = &mut prefix[..self.fixed_len()];
+        reader.read_exact(prefix).await?;
+        Ok(self.decode_fixed(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::Write;
+
+    /// Writer recording the size of each write.
+    struct Recorder {
+        data: [u8; 512],
+        len: usize,
+        writes: [usize; 8],
+        count: usize,
+    }
+
+    impl Recorder {
+        fn new() -> Self {
+            Self {
+                data: [0; 512],
+                len: 0,
+                writes: [0; 8],
+                count: 0,
+            }
+        }
+
+        fn data(&self) -> &[u8] {
+            &self.data[..self.len]
+        }
+
+        fn writes(&self) -> &[usize] {
+            &self.writes[..self.count]
+        }
+    }
+
+    impl crate::Io for Recorder {
+        type Error = core::convert::Infallible;
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.data[self.len..][..buf.len()].copy_from_slice(buf);
+            self.len += buf.len();
+            self.writes[self.count] = buf.len();
+            self.count += 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn prefix_encoding() {
+        let cases: [(LengthDelimited, &[u8]); 5] = [
+            (LengthDelimited::new(), &[0, 0, 0, 3]),
+            (LengthDelimited::new().endian(Endian::Little), &[3, 0, 0, 0]),
+            (
+                LengthDelimited::new().prefix_width(PrefixWidth::U16),
+                &[0, 3],
+            ),
+            (LengthDelimited::new().prefix_width(PrefixWidth::U8), &[3]),
+            (
+                LengthDelimited::new().prefix_width(PrefixWidth::Varint),
+                &[3],
+            ),
+        ];
+        for (codec, prefix) in cases {
+            let mut w = Recorder::new();
+            codec.write_frame(&mut w, b"abc").unwrap();
+            assert_eq!(&w.data()[..prefix.len()], prefix);
+            assert_eq!(&w.data()[prefix.len()..], b"abc");
+
+            let mut r = w.data();
+            let mut buf = [0; 8];
+            assert_eq!(codec.read_frame(&mut r, &mut buf), Ok(3));
+            assert_eq!(&buf[..3], b"abc");
+            assert!(r.is_empty());
+        }
+    }
+
+    #[test]
+    fn varint_prefix_round_trip() {
+        let codec = LengthDelimited::new().prefix_width(PrefixWidth::Varint);
+        let frame = [0x55; 300];
+        let mut w = Recorder::new();
+        codec.write_frame(&mut w, &frame).unwrap();
+        assert_eq!(&w.data()[..2], &[0xAC, 0x02]);
+        let mut buf = [0; 300];
+        assert_eq!(codec.read_frame(&mut w.data(), &mut buf), Ok(300));
+        assert_eq!(buf, frame);
+    }
+
+    #[test]
+    fn prefix_written_with_frame() {
+        let codec = LengthDelimited::new();
+        let mut w = Recorder::new();
+        codec.write_frame(&mut w, &[1; 60]).unwrap();
+        assert_eq!(w.writes(), &[64]);
+
+        let mut w = Recorder::new();
+        codec.write_frame(&mut w, &[1; 100]).unwrap();
+        assert_eq!(w.writes(), &[64, 40]);
+
+        let mut w = Recorder::new();
+        codec.write_frame(&mut w, &[]).unwrap();
+        assert_eq!(w.writes(), &[4]);
+    }
+
+    #[test]
+    fn write_failure_leaves_partial_frame() {
+        use crate::chaos::{Chaotic, ChaoticError, Fault};
+        use crate::ErrorKind;
+
+        let codec = LengthDelimited::new();
+        let mut buf = [0; 128];
+        let faults = [Fault::NONE, Fault::error(ErrorKind::Other)];
+        let mut w = Chaotic::new(&mut buf[..], faults.into_iter());
+        assert_eq!(
+            codec.write_frame(&mut w, &[1; 100]),
+            Err(FrameError::Other(ChaoticError::Injected(ErrorKind::Other)))
+        );
+        assert_eq!(w.into_inner().len(), 64);
+        assert_eq!(&buf[..4], &[0, 0, 0, 100]);
+    }
+
+    #[test]
+    fn write_oversized() {
+        let codec = LengthDelimited::new().prefix_width(PrefixWidth::U8);
+        let mut w = Recorder::new();
+        assert_eq!(
+            codec.write_frame(&mut w, &[0; 256]),
+            Err(FrameError::Oversized(256))
+        );
+        let codec = LengthDelimited::new().max_frame_len(4);
+        assert_eq!(
+            codec.write_frame(&mut w, &[0; 5]),
+            Err(FrameError::Oversized(5))
+        );
+        assert!(w.writes().is_empty());
+    }
+
+    #[test]
+    fn read_larger_than_buffer_is_skipped() {
+        let codec = LengthDelimited::new().prefix_width(PrefixWidth::U8);
+        let mut data = [0; 44];
+        data[0] = 40;
+        data[41..].copy_from_slice(b"\x02hi");
+        let mut r = &data[..];
+        let mut buf = [0; 8];
+        assert_eq!(
+            codec.read_frame(&mut r, &mut buf),
+            Err(FrameError::Oversized(40))
+        );
+        assert_eq!(codec.read_frame(&mut r, &mut buf), Ok(2));
+        assert_eq!(&buf[..2], b"hi");
+    }
+
+    #[test]
+    fn read_larger_than_max_is_not_skipped() {
+        let codec = LengthDelimited::new().max_frame_len(4);
+        let mut r = &[0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o'][..];
+        let mut buf = [0; 8];
+        assert_eq!(
+            codec.read_frame(&mut r, &mut buf),
+            Err(FrameError::Oversized(5))
+        );
+        assert_eq!(r, b"hello");
+    }
+
+    #[test]
+    fn read_malformed() {
+        let codec = LengthDelimited::new();
+        let mut buf = [0; 8];
+        assert_eq!(
+            codec.read_frame(&mut &[0, 0, 0, 3, b'a'][..], &mut buf),
+            Err(FrameError::UnexpectedEof)
+        );
+        assert_eq!(
+            codec.read_frame(&mut &[0, 0][..], &mut buf),
+            Err(FrameError::UnexpectedEof)
+        );
+
+        let codec = LengthDelimited::new().prefix_width(PrefixWidth::Varint);
+        assert_eq!(
+            codec.read_frame(&mut &[0xFF; 11][..], &mut buf),
+            Err(FrameError::Corrupt)
+        );
+        assert_eq!(
+            codec.read_frame(&mut &[0x80][..], &mut buf),
+            Err(FrameError::UnexpectedEof)
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+
+        let codec = LengthDelimited::new().prefix_width(PrefixWidth::U16);
+        let mut data = [0; 16];
+        let mut w = &mut data[..];
+        block_on(codec.write_frame_async(&mut w, b"hello"), &mut Spin).unwrap();
+        let len = 16 - w.len();
+        assert_eq!(&data[..len], b"\0\x05hello");
+
+        let mut buf = [0; 8];
+        let res = block_on(
+            codec.read_frame_async(&mut &data[..len], &mut buf),
+            &mut Spin,
+        );
+        assert_eq!(res, Ok(5));
+        assert_eq!(&buf[..5], b"hello");
+    }
+}
@@ -0,0 +1,218 @@
+//! Framing of message-oriented protocols over byte streams.
+//!
+//! Each codec turns a stream implementing the [`blocking`](crate::blocking) or
+//! [`asynch`](crate::asynch) IO traits into a sequence of frames.
+
+use core::fmt;
+
+use crate::blocking::{ReadExactError, WriteAllError};
+
+mod cobs;
+mod hdlc;
+mod length_delimited;
+mod slip;
+pub use cobs::*;
+pub use hdlc::*;
+pub use length_delimited::*;
+pub use slip::*;
+
+/// Error returned by the framing codecs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameError<E> {
+    /// The frame is larger than allowed. Contains the frame length.
+    ///
+    /// When reading, this means the frame exceeds either the maximum frame length or the
+    /// provided buffer. When writing, the frame exceeds the maximum frame length, and nothing
+    /// was written.
+    Oversized(usize),
+    /// The frame is malformed, for example a truncated encoding or an invalid length prefix.
+    Corrupt,
+    /// An EOF error was encountered before reading a whole frame.
+    UnexpectedEof,
+    /// [`Write::write`](crate::blocking::Write::write) wrote zero bytes.
+    WriteZero,
+    /// Error returned by the inner IO object.
+    Other(E),
+}
+
+impl<E> From<E> for FrameError<E> {
+    fn from(err: E) -> Self {
+        Self::Other(err)
+    }
+}
+
+impl<E> From<ReadExactError<E>> for FrameError<E> {
+    fn from(err: ReadExactError<E>) -> Self {
+        match err {
+            ReadExactError::UnexpectedEof => Self::UnexpectedEof,
+            ReadExactError::Other(e) => Self::Other(e),
+        }
+    }
+}
+
+impl<E> From<WriteAllError<E>> for FrameError<E> {
+    fn from(err: WriteAllError<E>) -> Self {
+        match err {
+            WriteAllError::WriteZero => Self::WriteZero,
+            WriteAllError::Other(e) => Self::Other(e),
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for FrameError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for FrameError<E> {}
+
+impl<E: crate::Error> crate::Error for FrameError<E> {
+    fn kind(&self) -> crate::ErrorKind {
+        match self {
+            Self::Oversized(_) | Self::Corrupt => crate::ErrorKind::InvalidData,
+            Self::UnexpectedEof => crate::ErrorKind::UnexpectedEof,
+            Self::WriteZero => crate::ErrorKind::WriteZero,
+            Self::Other(e) => e.kind(),
+        }
+    }
+}
+
+/// Size of the chunks escaped bytes are written to the inner writer in.
+const ESCAPE_CHUNK: usize = 64;
+
+/// Escape as much of `buf` as fits in `out`, returning the number of bytes consumed and produced.
+///
+/// Bytes for which `escape` returns `Some(b)` are replaced by `esc` followed by `b`.
+fn escape(
+    buf: &[u8],
+    esc: u8,
+    escape: impl Fn(u8) -> Option<u8>,
+    out: &mut [u8; ESCAPE_CHUNK],
+) -> (usize, usize) {
+    let mut consumed = 0;
+    let mut produced = 0;
+    for &byte in buf {
+        let escaped = escape(byte);
+        let len = if escaped.is_some() { 2 } else { 1 };
+        if produced + len > out.len() {
+            break;
+        }
+        match escaped {
+            Some(b) => out[produced..][..2].copy_from_slice(&[esc, b]),
+            None => out[produced] = byte,
+        }
+        produced += len;
+        consumed += 1;
+    }
+    (consumed, produced)
+}
+
+/// Write all of `buf`, escaped as described in [`escape`].
+fn write_escaped<W: crate::blocking::Write + ?Sized>(
+    writer: &mut W,
+    mut buf: &[u8],
+    esc: u8,
+    escape: impl Fn(u8) -> Option<u8>,
+) -> Result<(), FrameError<W::Error>> {
+    let mut out = [0; ESCAPE_CHUNK];
+    while !buf.is_empty() {
+        let (consumed, produced) = self::escape(buf, esc, &escape, &mut out);
+        writer.write_all(&out[..produced])?;
+        buf = &buf[consumed..];
+    }
+    Ok(())
+}
+
+/// Write all of `buf`, escaped as described in [`escape`].
+#[cfg(feature = "async")]
+async fn write_escaped_async<W: crate::asynch::Write + ?Sized>(
+    writer: &mut W,
+    mut buf: &[u8],
+    esc: u8,
+    escape: impl Fn(u8) -> Option<u8>,
+) -> Result<(), FrameError<W::Error>> {
+    let mut out = [0; ESCAPE_CHUNK];
+    while !buf.is_empty() {
+        let (consumed, produced) = self::escape(buf, esc, &escape, &mut out);
+        writer.write_all(&out[..produced]).await?;
+        buf = &buf[consumed..];
+    }
+    Ok(())
+}
+
+/// Streaming decoder of a frame encoding with delimiters.
+trait Decode: Default {
+    /// Feed a byte, returning the result once a frame delimiter is reached.
+    fn feed<E>(&mut self, byte: u8, buf: &mut [u8]) -> Option<Result<usize, FrameError<E>>>;
+
+    /// Whether part of a frame has been decoded.
+    fn in_frame(&self) -> bool;
+}
+
+/// Feed bytes from `data` until a frame is complete, returning the number of bytes used, and
+/// the frame's result if it's complete.
+fn decode<D: Decode, E>(
+    decoder: &mut D,
+    data: &[u8],
+    buf: &mut [u8],
+) -> (usize, Option<Result<usize, FrameError<E>>>) {
+    for (i, &byte) in data.iter().enumerate() {
+        if let Some(res) = decoder.feed(byte, buf) {
+            return (i + 1, Some(res));
+        }
+    }
+    (data.len(), None)
+}
+
+/// Handle EOF from the inner reader, which is only an error in the middle of a frame.
+fn decode_eof<D: Decode, E>(decoder: &mut D) -> Result<Option<usize>, FrameError<E>> {
+    let in_frame = decoder.in_frame();
+    *decoder = D::default();
+    if in_frame {
+        Err(FrameError::UnexpectedEof)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Read and decode the next frame into `buf`.
+fn read_frame<R: crate::blocking::BufRead + ?Sized, D: Decode>(
+    reader: &mut R,
+    decoder: &mut D,
+    buf: &mut [u8],
+) -> Result<Option<usize>, FrameError<R::Error>> {
+    loop {
+        let data = reader.fill_buf()?;
+        if data.is_empty() {
+            return decode_eof(decoder);
+        }
+        let (used, res) = decode(decoder, data, buf);
+        reader.consume(used);
+        if let Some(res) = res {
+            return res.map(Some);
+        }
+    }
+}
+
+/// Read and decode the next frame into `buf`.
+#[cfg(feature = "async")]
+async fn read_frame_async<R: crate::asynch::BufRead + ?Sized, D: Decode>(
+    reader: &mut R,
+    decoder: &mut D,
+    buf: &mut [u8],
+) -> Result<Option<usize>, FrameError<R::Error>> {
+    loop {
+        let data = reader.fill_buf().await?;
+        if data.is_empty() {
+            return decode_eof(decoder);
+        }
+        let (used, res) = decode(decoder, data, buf);
+        reader.consume(used);
+        if let Some(res) = res {
+            return res.map(Some);
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use super::{Decode, FrameError};
+
+const END: u8 = 0xC0;
+const ESC: u8 = 0xDB;
+const ESC_END: u8 = 0xDC;
+const ESC_ESC: u8 = 0xDD;
+
+/// Escape sequence for `byte`, if it needs one.
+fn escape(byte: u8) -> Option<u8> {
+    match byte {
+        END => Some(ESC_END),
+        ESC => Some(ESC_ESC),
+        _ => None,
+    }
+}
+
+/// Writer of SLIP-encoded frames, as specified in RFC 1055.
+///
+/// Bytes written are escaped on the fly. Each frame is preceded and terminated by an `END`
+/// byte, as recommended by the RFC to flush out line noise. A frame is terminated by
+/// [`end_frame`](Self::end_frame), or by `flush` if anything was written since the previous
+/// frame.
+pub struct SlipWriter<W> {
+    inner: W,
+    /// Data has been written since the last frame delimiter.
+    in_frame: bool,
+}
+
+impl<W> SlipWriter<W> {
+    /// Create a new SLIP writer.
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            in_frame: false,
+        }
+    }
+
+    /// Consume the writer, returning the inner object.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: crate::blocking::Write> SlipWriter<W> {
+    /// Terminate the current frame, even if empty.
+    ///
+    /// This does not flush the inner writer.
+    pub fn end_frame(&mut self) -> Result<(), FrameError<W::Error>> {
+        self.in_frame = false;
+        self.inner.write_all(&[END])?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<W: crate::asynch::Write> SlipWriter<W> {
+    /// Terminate the current frame, even if empty.
+    ///
+    /// This does not flush the inner writer.
+    pub async fn end_frame_async(&mut self) -> Result<(), FrameError<W::Error>> {
+        self.in_frame = false;
+        self.inner.write_all(&[END]).await?;
+        Ok(())
+    }
+}
+
+impl<W: crate::Io> crate::Io for SlipWriter<W> {
+    type Error = FrameError<W::Error>;
+}
+
+impl<W: crate::blocking::Write> crate::blocking::Write for SlipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if !self.in_frame && !buf.is_empty() {
+            self.inner.write_all(&[END])?;
+            self.in_frame = true;
+        }
+        super::write_escaped(&mut self.inner, buf, ESC, escape)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.in_frame {
+            self.end_frame()?;
+        }
+        Ok(self.inner.flush()?)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<W: crate::asynch::Write> crate::asynch::Write for SlipWriter<W> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if !self.in_frame && !buf.is_empty() {
+            self.inner.write_all(&[END]).await?;
+            self.in_frame = true;
+        }
+        super::write_escaped_async(&mut self.inner, buf, ESC, escape).await?;
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        if self.in_frame {
+            self.end_frame_async().await?;
+        }
+        Ok(self.inner.flush().await?)
+    }
+}
+
+/// Streaming SLIP decoder state.
+#[derive(Default)]
+struct Decoder {
+    /// The previous byte was `ESC`.
+    escape: bool,
+    /// An invalid escape sequence was found in the current frame.
+    corrupt: bool,
+    /// Decoded length so far. May exceed the buffer, for reporting oversized frames.
+    len: usize,
+}
+
+impl Decode for Decoder {
+    /// Empty frames are skipped, so leading and redundant `END`s are harmless.
+    fn feed<E>(&mut self, byte: u8, buf: &mut [u8]) -> Option<Result<usize, FrameError<E>>> {
+        if byte == END {
+            let res = if self.corrupt || self.escape {
+                Some(Err(FrameError::Corrupt))
+            } else if self.len == 0 {
+                None
+            } else if self.len > buf.len() {
+                Some(Err(FrameError::Oversized(self.len)))
+            } else {
+                Some(Ok(self.len))
+            };
+            *self = Self::default();
+            return res;
+        }
+
+        let byte = if self.escape {
+            self.escape = false;
+            match byte {
+                ESC_END => END,
+                ESC_ESC => ESC,
+                _ => {
+                    self.corrupt = true;
+                    return None;
+                }
+            }
+        } else if byte == ESC {
+            self.escape = true;
+            return None;
+        } else {
+            byte
+        };
+
+        if let Some(b) = buf.get_mut(self.len) {
+            *b = byte;
+        }
+        self.len += 1;
+        None
+    }
+
+    fn in_frame(&self) -> bool {
+        self.len != 0 || self.escape || self.corrupt
+    }
+}
+
+/// Reader of SLIP-encoded frames, as specified in RFC 1055.
+///
+/// Frames are decoded into a buffer provided by the caller. Frames with invalid escape
+/// sequences and oversized frames are reported as errors, after which the reader
+/// resynchronizes on the next `END` byte.
+pub struct SlipReader<R> {
+    inner: R,
+    decoder: Decoder,
+}
+
+impl<R> SlipReader<R> {
+    /// Create a new SLIP reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            decoder: Decoder::default(),
+        }
+    }
+
+    /// Consume the reader, returning the inner object.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+impl<R: crate::blocking::BufRead> SlipReader<R> {
+    /// Read and decode the next frame into `buf`, returning its length.
+    ///
+    /// Returns `Ok(None)` if the stream ends between frames, and [`FrameError::UnexpectedEof`]
+    /// if it ends in the middle of one. If the inner reader fails mid-frame, calling this again
+    /// with the same buffer resumes decoding where it left off.
+    pub fn read_frame(&mut self, buf: &mut [u8]) -> Result<Option<usize>, FrameError<R::Error>> {
+        super::read_frame(&mut self.inner, &mut self.decoder, buf)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<R: crate::asynch::BufRead> SlipReader<R> {
+    /// Read and decode the next frame into `buf`, returning its length.
+    ///
+    /// See [`read_frame`](Self::read_frame) for details.
+    pub async fn read_frame_async(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<usize>, FrameError<R::Error>> {
+        super::read_frame_async(&mut self.inner, &mut self.decoder, buf).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::Write;
+
+    fn encode(frames: &[&[u8]], out: &mut [u8]) -> usize {
+        let total = out.len();
+        let mut w = SlipWriter::new(&mut *out);
+        for frame in frames {
+            w.write_all(frame).unwrap();
+            w.end_frame().unwrap();
+        }
+        total - w.into_inner().len()
+    }
+
+    #[test]
+    fn escapes() {
+        let mut out = [0; 32];
+        let n = encode(&[&[0x01, END, 0x02, ESC, ESC_END, ESC_ESC]], &mut out);
+        assert_eq!(
+            &out[..n],
+            &[END, 0x01, ESC, ESC_END, 0x02, ESC, ESC_ESC, ESC_END, ESC_ESC, END]
+        );
+
+        let mut r = SlipReader::new(&out[..n]);
+        let mut buf = [0; 8];
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(6)));
+        assert_eq!(&buf[..6], &[0x01, END, 0x02, ESC, ESC_END, ESC_ESC]);
+        assert_eq!(r.read_frame(&mut buf), Ok(None));
+    }
+
+    #[test]
+    fn round_trip() {
+        // Enough escapes to span several chunks.
+        let mut frame = [0; 200];
+        for (i, b) in frame.iter_mut().enumerate() {
+            *b = [END, ESC, i as u8][i % 3];
+        }
+        let mut out = [0; 600];
+        let n = encode(&[&frame, b"", b"next"], &mut out);
+
+        let mut r = SlipReader::new(&out[..n]);
+        let mut buf = [0; 200];
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(200)));
+        assert_eq!(buf, frame);
+        // The empty frame is skipped.
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(4)));
+        assert_eq!(&buf[..4], b"next");
+        assert_eq!(r.read_frame(&mut buf), Ok(None));
+    }
+
+    #[test]
+    fn flush_ends_frame() {
+        let mut out = [0; 16];
+        let mut w = SlipWriter::new(&mut out[..]);
+        w.flush().unwrap();
+        w.write_all(b"ab").unwrap();
+        w.write_all(b"c").unwrap();
+        w.flush().unwrap();
+        w.flush().unwrap();
+        assert_eq!(w.into_inner().len(), 11);
+        assert_eq!(&out[..5], &[END, b'a', b'b', b'c', END]);
+    }
+
+    #[test]
+    fn malformed() {
+        let mut buf = [0; 4];
+
+        // Invalid escape sequence, then decoding resumes.
+        let mut r = SlipReader::new(&[0x01, ESC, 0x02, 0x03, END, 0x04, END][..]);
+        assert_eq!(r.read_frame(&mut buf), Err(FrameError::Corrupt));
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(1)));
+        assert_eq!(buf[0], 0x04);
+
+        // Escape right before the end.
+        let mut r = SlipReader::new(&[0x01, ESC, END][..]);
+        assert_eq!(r.read_frame(&mut buf), Err(FrameError::Corrupt));
+
+        // Too large for the buffer, then decoding resumes.
+        let mut r = SlipReader::new(&[1, 2, 3, 4, 5, END, 6, END][..]);
+        assert_eq!(r.read_frame(&mut buf), Err(FrameError::Oversized(5)));
+        assert_eq!(r.read_frame(&mut buf), Ok(Some(1)));
+        assert_eq!(buf[0], 6);
+    }
+
+    #[test]
+    fn eof() {
+        let mut buf = [0; 4];
+        assert_eq!(SlipReader::new(&[][..]).read_frame(&mut buf), Ok(None));
+        assert_eq!(
+            SlipReader::new(&[END, END][..]).read_frame(&mut buf),
+            Ok(None)
+        );
+
+        let mut r = SlipReader::new(&[END, 0x01][..]);
+        assert_eq!(r.read_frame(&mut buf), Err(FrameError::UnexpectedEof));
+        assert_eq!(r.read_frame(&mut buf), Ok(None));
+
+        let mut r = SlipReader::new(&[ESC][..]);
+        assert_eq!(r.read_frame(&mut buf), Err(FrameError::UnexpectedEof));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+
+        let mut out = [0; 16];
+        let mut w = SlipWriter::new(&mut out[..]);
+        block_on(
+            async {
+                crate::asynch::Write::write_all(&mut w, &[END])
+                    .await
+                    .unwrap();
+                crate::asynch::Write::flush(&mut w).await.unwrap();
+            },
+            &mut Spin,
+        );
+        assert_eq!(w.into_inner().len(), 12);
+        assert_eq!(&out[..4], &[END, ESC, ESC_END, END]);
+
+        let mut r = SlipReader::new(&out[..4]);
+        let mut buf = [0; 4];
+        assert_eq!(
+            block_on(r.read_frame_async(&mut buf), &mut Spin),
+            Ok(Some(1))
+        );
+        assert_eq!(block_on(r.read_frame_async(&mut buf), &mut Spin), Ok(None));
+        assert_eq!(buf[0], END);
+    }
+}
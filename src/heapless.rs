@@ -0,0 +1,517 @@
+//! Integration with [`heapless`] collections.
+//!
+//! [`heapless::Vec<u8, N>`] implements [`Write`](crate::blocking::Write), appending to the vector,
+//! and [`heapless::Deque<u8, N>`] implements [`Read`](crate::blocking::Read) and
+//! [`BufRead`](crate::blocking::BufRead), popping from the front of the queue. Both have a fixed
+//! capacity: writes to a full vector are short, ultimately `Ok(0)`.
+//!
+//! [`read_to_end`] and [`read_line`] collect a reader's data into a [`heapless::Vec`] or
+//! [`heapless::String`], failing with [`ReadIntoError::Full`] if it doesn't fit.
+
+use core::fmt;
+
+use heapless::{Deque, String, Vec};
+
+/// Error returned by [`read_to_end`] and [`read_line`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadIntoError<E> {
+    /// The destination filled up before reaching the end of the data.
+    Full,
+    /// The line read isn't valid UTF-8.
+    InvalidUtf8,
+    /// Error returned by the inner reader.
+    Other(E),
+}
+
+impl<E> From<E> for ReadIntoError<E> {
+    fn from(err: E) -> Self {
+        Self::Other(err)
+    }
+}
+
+impl<E: crate::Error> crate::Error for ReadIntoError<E> {
+    fn kind(&self) -> crate::ErrorKind {
+        match self {
+            Self::Full => crate::ErrorKind::OutOfMemory,
+            Self::InvalidUtf8 => crate::ErrorKind::InvalidData,
+            Self::Other(e) => e.kind(),
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for ReadIntoError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for ReadIntoError<E> {}
+
+impl<const N: usize> crate::Io for Vec<u8, N> {
+    type Error = core::convert::Infallible;
+}
+
+impl<const N: usize> crate::Io for Deque<u8, N> {
+    type Error = core::convert::Infallible;
+}
+
+/// Append as much of `buf` as fits in `vec`.
+fn extend<const N: usize>(vec: &mut Vec<u8, N>, buf: &[u8]) -> usize {
+    let amt = core::cmp::min(buf.len(), vec.capacity() - vec.len());
+    unwrap!(vec.extend_from_slice(&buf[..amt]));
+    amt
+}
+
+/// Pop bytes from the front of `deque` into `buf`.
+fn pop<const N: usize>(deque: &mut Deque<u8, N>, buf: &mut [u8]) -> usize {
+    let mut amt = 0;
+    while amt < buf.len() {
+        let (front, _) = deque.as_slices();
+        if front.is_empty() {
+            break;
+        }
+        let n = core::cmp::min(front.len(), buf.len() - amt);
+        buf[amt..amt + n].copy_from_slice(&front[..n]);
+        consume(deque, n);
+        amt += n;
+    }
+    amt
+}
+
+fn consume<const N: usize>(deque: &mut Deque<u8, N>, amt: usize) {
+    for _ in 0..amt {
+        deque.pop_front();
+    }
+}
+
+enum Step {
+    More,
+    Done,
+    Full,
+}
+
+/// Append the data available from a reader to `vec`, up to and including the first newline if
+/// `line` is set. Returns how many bytes were used, and whether to continue.
+fn append<const N: usize>(vec: &mut Vec<u8, N>, avail: &[u8], line: bool) -> (usize, Step) {
+    if avail.is_empty() {
+        return (0, Step::Done);
+    }
+    let newline = if line {
+        avail.iter().position(|&b| b == b'\n')
+    } else {
+        None
+    };
+    let (end, step) = match newline {
+        Some(i) => (i + 1, Step::Done),
+        None => (avail.len(), Step::More),
+    };
+
+    let room = vec.capacity() - vec.len();
+    if end > room {
+        // Leave a character that doesn't fit entirely in the reader.
+        let mut amt = room;
+        while line && amt > 0 && avail[amt] & 0xC0 == 0x80 {
+            amt -= 1;
+        }
+        unwrap!(vec.extend_from_slice(&avail[..amt]));
+        return (amt, Step::Full);
+    }
+    unwrap!(vec.extend_from_slice(&avail[..end]));
+    (end, step)
+}
+
+/// Truncates the string's bytes back to `len` when dropped, so that it stays valid UTF-8 even if
+/// the read fails, panics or is cancelled.
+struct Guard<'a, const N: usize> {
+    vec: &'a mut Vec<u8, N>,
+    len: usize,
+}
+
+impl<const N: usize> Drop for Guard<'_, N> {
+    fn drop(&mut self) {
+        self.vec.truncate(self.len);
+    }
+}
+
+impl<const N: usize> Guard<'_, N> {
+    /// Keep the appended bytes if they're valid UTF-8.
+    ///
+    /// If the read failed after part of a character, the bytes before it are kept.
+    fn finish<E>(
+        mut self,
+        res: Result<usize, ReadIntoError<E>>,
+    ) -> Result<usize, ReadIntoError<E>> {
+        match core::str::from_utf8(&self.vec[self.len..]) {
+            Ok(_) => self.len = self.vec.len(),
+            Err(e) if res.is_err() && e.error_len().is_none() => self.len += e.valid_up_to(),
+            Err(_) => return res.and(Err(ReadIntoError::InvalidUtf8)),
+        }
+        res
+    }
+}
+
+/// Read all bytes until EOF, appending them to `buf`.
+///
+/// Returns the number of bytes appended. If `buf` fills up first, as much data as fits is
+/// appended and [`ReadIntoError::Full`] is returned, leaving the rest in the reader.
+pub fn read_to_end<R: crate::blocking::BufRead + ?Sized, const N: usize>(
+    reader: &mut R,
+    buf: &mut Vec<u8, N>,
+) -> Result<usize, ReadIntoError<R::Error>> {
+    read_until(reader, buf, false)
+}
+
+/// Read a line, appending it to `buf`.
+///
+/// The line includes its terminating newline, if any. Returns the number of bytes appended,
+/// which is 0 at EOF.
+///
+/// If the line doesn't fit, as many whole characters as fit are appended and
+/// [`ReadIntoError::Full`] is returned, leaving the rest in the reader. If the line isn't valid
+/// UTF-8, [`ReadIntoError::InvalidUtf8`] is returned and `buf` is left unchanged. If the reader
+/// fails, the data read before is appended, except for a partial character at its end.
+pub fn read_line<R: crate::blocking::BufRead + ?Sized, const N: usize>(
+    reader: &mut R,
+    buf: &mut String<N>,
+) -> Result<usize, ReadIntoError<R::Error>> {
+    // Safety: the guard truncates any bytes that aren't valid UTF-8.
+    let vec = unsafe { buf.as_mut_vec() };
+    let len = vec.len();
+    let guard = Guard { vec, len };
+    let res = read_until(reader, guard.vec, true);
+    guard.finish(res)
+}
+
+fn read_until<R: crate::blocking::BufRead + ?Sized, const N: usize>(
+    reader: &mut R,
+    buf: &mut Vec<u8, N>,
+    line: bool,
+) -> Result<usize, ReadIntoError<R::Error>> {
+    let start = buf.len();
+    loop {
+        let (amt, step) = append(buf, reader.fill_buf()?, line);
+        reader.consume(amt);
+        match step {
+            Step::More => {}
+            Step::Done => return Ok(buf.len() - start),
+            Step::Full => return Err(ReadIntoError::Full),
+        }
+    }
+}
+
+/// Async version of [`read_to_end`].
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub async fn read_to_end_async<R: crate::asynch::BufRead + ?Sized, const N: usize>(
+    reader: &mut R,
+    buf: &mut Vec<u8, N>,
+) -> Result<usize, ReadIntoError<R::Error>> {
+    read_until_async(reader, buf, false).await
+}
+
+/// Async version of [`read_line`].
+///
+/// If the future is dropped before completing, `buf` is left unchanged, but the data read so far
+/// is lost.
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub async fn read_line_async<R: crate::asynch::BufRead + ?Sized, const N: usize>(
+    reader: &mut R,
+    buf: &mut String<N>,
+) -> Result<usize, ReadIntoError<R::Error>> {
+    // Safety: the guard truncates any bytes that aren't valid UTF-8.
+    let vec = unsafe { buf.as_mut_vec() };
+    let len = vec.len();
+    let guard = Guard { vec, len };
+    let res = read_until_async(reader, guard.vec, true).await;
+    guard.finish(res)
+}
+
+#[cfg(feature = "async")]
+async fn read_until_async<R: crate::asynch::BufRead + ?Sized, const N: usize>(
+    reader: &mut R,
+    buf: &mut Vec<u8, N>,
+    line: bool,
+) -> Result<usize, ReadIntoError<R::Error>> {
+    let start = buf.len();
+    loop {
+        let (amt, step) = append(buf, reader.fill_buf().await?, line);
+        reader.consume(amt);
+        match step {
+            Step::More => {}
+            Step::Done => return Ok(buf.len() - start),
+            Step::Full => return Err(ReadIntoError::Full),
+        }
+    }
+}
+
+/// Write is implemented for `heapless::Vec<u8, N>` by appending to the vector.
+///
+/// If the vector is full, write operations return short writes: ultimately, `Ok(0)`; in this
+/// situation, `write_all` returns an error of kind `ErrorKind::WriteZero`.
+impl<const N: usize> crate::blocking::Write for Vec<u8, N> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(extend(self, buf))
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<const N: usize> crate::blocking::WriteReady for Vec<u8, N> {
+    #[inline]
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// Read is implemented for `heapless::Deque<u8, N>` by popping from the front of the queue.
+///
+/// Reads return `Ok(0)` once the queue is empty.
+impl<const N: usize> crate::blocking::Read for Deque<u8, N> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(pop(self, buf))
+    }
+}
+
+/// `fill_buf` returns the contiguous bytes at the front of the queue, which may be fewer than
+/// its length if the data wraps around the end of its storage.
+impl<const N: usize> crate::blocking::BufRead for Deque<u8, N> {
+    #[inline]
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        Ok(self.as_slices().0)
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        consume(self, amt)
+    }
+}
+
+impl<const N: usize> crate::blocking::ReadReady for Deque<u8, N> {
+    #[inline]
+    fn read_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<const N: usize> crate::asynch::Write for Vec<u8, N> {
+    #[inline]
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(extend(self, buf))
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<const N: usize> crate::asynch::Read for Deque<u8, N> {
+    #[inline]
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        Ok(pop(self, buf))
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<const N: usize> crate::asynch::BufRead for Deque<u8, N> {
+    #[inline]
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        Ok(self.as_slices().0)
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        consume(self, amt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::{BufRead, Read, Write, WriteAllError};
+    use crate::chaos::{Chaotic, ChaoticError, Fault};
+    use crate::ErrorKind;
+
+    #[test]
+    fn vec_write() {
+        let mut vec = Vec::<u8, 4>::new();
+        assert_eq!(vec.write(b"abc"), Ok(3));
+        assert_eq!(vec.write(b"de"), Ok(1));
+        assert_eq!(vec.write(b"f"), Ok(0));
+        assert_eq!(vec.write(b""), Ok(0));
+        assert_eq!(vec.flush(), Ok(()));
+        assert_eq!(&vec[..], b"abcd");
+
+        let mut vec = Vec::<u8, 4>::new();
+        assert_eq!(vec.write_all(b"abcde"), Err(WriteAllError::WriteZero));
+        assert_eq!(&vec[..], b"abcd");
+    }
+
+    #[test]
+    fn deque_read() {
+        let mut deque = Deque::<u8, 4>::new();
+        for b in *b"abcd" {
+            unwrap!(deque.push_back(b));
+        }
+        let mut buf = [0; 2];
+        assert_eq!(deque.read(&mut buf), Ok(2));
+        assert_eq!(&buf, b"ab");
+        unwrap!(deque.push_back(b'e'));
+        unwrap!(deque.push_back(b'f'));
+
+        // The data wraps around the end of the storage.
+        assert_eq!(deque.fill_buf(), Ok(&b"cd"[..]));
+        deque.consume(1);
+        let mut buf = [0; 8];
+        assert_eq!(deque.read(&mut buf), Ok(3));
+        assert_eq!(&buf[..3], b"def");
+        assert_eq!(deque.read(&mut buf), Ok(0));
+        assert_eq!(deque.fill_buf(), Ok(&b""[..]));
+    }
+
+    #[test]
+    fn read_to_end_full() {
+        let mut reader = Chaotic::new(&b"hello"[..], core::iter::repeat(Fault::limit(2)));
+        let mut vec = Vec::<u8, 8>::new();
+        assert_eq!(read_to_end(&mut reader, &mut vec), Ok(5));
+        assert_eq!(&vec[..], b"hello");
+
+        let mut reader = &b"hello"[..];
+        let mut vec = Vec::<u8, 4>::new();
+        unwrap!(vec.push(b'>'));
+        assert_eq!(read_to_end(&mut reader, &mut vec), Err(ReadIntoError::Full));
+        assert_eq!(&vec[..], b">hel");
+        assert_eq!(reader, b"lo");
+
+        // A buffer filled exactly at EOF isn't full, and reading again appends nothing.
+        let mut reader = &b"abcd"[..];
+        let mut vec = Vec::<u8, 4>::new();
+        assert_eq!(read_to_end(&mut reader, &mut vec), Ok(4));
+        assert_eq!(read_to_end(&mut reader, &mut vec), Ok(0));
+    }
+
+    #[test]
+    fn read_line_full() {
+        let mut reader = "abcé\nxy".as_bytes();
+        let mut line = String::<4>::new();
+        assert_eq!(read_line(&mut reader, &mut line), Err(ReadIntoError::Full));
+        assert_eq!(line, "abc");
+        assert_eq!(reader, "é\nxy".as_bytes());
+
+        line.clear();
+        assert_eq!(read_line(&mut reader, &mut line), Ok(3));
+        assert_eq!(line, "é\n");
+        line.clear();
+        assert_eq!(read_line(&mut reader, &mut line), Ok(2));
+        assert_eq!(line, "xy");
+        line.clear();
+        assert_eq!(read_line(&mut reader, &mut line), Ok(0));
+        assert_eq!(line, "");
+    }
+
+    #[test]
+    fn read_line_errors() {
+        let mut reader = &b"a\xffb\nrest"[..];
+        let mut line = String::<8>::new();
+        unwrap!(line.push('>'));
+        assert_eq!(
+            read_line(&mut reader, &mut line),
+            Err(ReadIntoError::InvalidUtf8)
+        );
+        assert_eq!(line, ">");
+        assert_eq!(read_line(&mut reader, &mut line), Ok(4));
+        assert_eq!(line, ">rest");
+
+        // A character split across reads is reassembled.
+        let faults = [Fault::limit(2), Fault::limit(1)];
+        let mut reader = Chaotic::new("aé\n".as_bytes(), faults.into_iter());
+        let mut line = String::<8>::new();
+        assert_eq!(read_line(&mut reader, &mut line), Ok(4));
+        assert_eq!(line, "aé\n");
+
+        // Valid data read before an error is kept.
+        let faults = [Fault::limit(2), Fault::error(ErrorKind::Other)];
+        let mut reader = Chaotic::new(&b"abc\n"[..], faults.into_iter());
+        let mut line = String::<8>::new();
+        let err = ReadIntoError::Other(ChaoticError::Injected(ErrorKind::Other));
+        assert_eq!(read_line(&mut reader, &mut line), Err(err));
+        assert_eq!(line, "ab");
+        assert_eq!(read_line(&mut reader, &mut line), Ok(2));
+        assert_eq!(line, "abc\n");
+
+        // A partial character before an error isn't, but the data before it is.
+        let faults = [Fault::limit(2), Fault::error(ErrorKind::Other)];
+        let mut reader = Chaotic::new("aé\n".as_bytes(), faults.into_iter());
+        let mut line = String::<8>::new();
+        assert_eq!(read_line(&mut reader, &mut line), Err(err));
+        assert_eq!(line, "a");
+    }
+
+    #[test]
+    fn error_kinds() {
+        use crate::Error;
+
+        assert_eq!(
+            ReadIntoError::<ErrorKind>::Full.kind(),
+            ErrorKind::OutOfMemory
+        );
+        assert_eq!(
+            ReadIntoError::<ErrorKind>::InvalidUtf8.kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+        use futures::FutureExt;
+
+        let mut vec = Vec::<u8, 4>::new();
+        assert_eq!(
+            block_on(crate::asynch::Write::write(&mut vec, b"abcde"), &mut Spin),
+            Ok(4)
+        );
+        let mut deque = Deque::<u8, 4>::new();
+        unwrap!(deque.push_back(b'a'));
+        let mut buf = [0; 4];
+        let read = crate::asynch::Read::read(&mut deque, &mut buf);
+        assert_eq!(block_on(read, &mut Spin), Ok(1));
+
+        let faults = core::iter::repeat(Fault::limit(2).with_pending(1));
+        let mut reader = Chaotic::new(&b"hello"[..], faults);
+        let mut vec = Vec::<u8, 4>::new();
+        let mut polls = 0;
+        assert_eq!(
+            block_on(read_to_end_async(&mut reader, &mut vec), &mut || polls += 1),
+            Err(ReadIntoError::Full)
+        );
+        assert_eq!(polls, 3);
+        assert_eq!(&vec[..], b"hell");
+        assert_eq!(reader.into_inner(), b"o");
+
+        let mut reader = "abcé\nxy".as_bytes();
+        let mut line = String::<4>::new();
+        assert_eq!(
+            block_on(read_line_async(&mut reader, &mut line), &mut Spin),
+            Err(ReadIntoError::Full)
+        );
+        assert_eq!(line, "abc");
+
+        // Dropping the future mid-character leaves the line unchanged.
+        let faults = [Fault::limit(2), Fault::NONE.with_pending(1)];
+        let mut reader = Chaotic::new("aé\n".as_bytes(), faults.into_iter());
+        let mut line = String::<8>::new();
+        unwrap!(line.push('>'));
+        assert_eq!(read_line_async(&mut reader, &mut line).now_or_never(), None);
+        assert_eq!(line, ">");
+    }
+}
@@ -17,6 +17,27 @@ pub mod asynch;
 pub mod blocking;
 
 pub mod adapters;
+#[cfg(feature = "bytes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+pub mod bytes;
+pub mod chaos;
+pub mod framing;
+#[cfg(feature = "heapless")]
+#[cfg_attr(docsrs, doc(cfg(feature = "heapless")))]
+pub mod heapless;
+pub mod num;
+#[cfg(any(feature = "log", feature = "defmt"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "log", feature = "defmt"))))]
+pub mod traced;
+pub mod varint;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "std", feature = "alloc"))))]
+pub mod mock;
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub mod timeout;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -25,6 +46,18 @@ pub mod adapters;
 pub enum ErrorKind {
     /// Unspecified error kind.
     Other,
+    /// The operation did not complete within the allotted time.
+    TimedOut,
+    /// The data is not valid for the operation, for example a corrupt frame.
+    InvalidData,
+    /// An operation could not be completed because a call to `write` returned `Ok(0)`.
+    WriteZero,
+    /// An operation could not be completed because EOF was reached prematurely.
+    UnexpectedEof,
+    /// A parameter was incorrect, for example seeking to a negative position.
+    InvalidInput,
+    /// An operation could not be completed because the destination is full.
+    OutOfMemory,
 }
 
 /// Error trait.
@@ -0,0 +1,440 @@
+//! Scripted mock IO object, for testing drivers.
+//!
+//! A [`Mock`] is built from a list of [`Transaction`]s describing exactly what the code under
+//! test is expected to do. Writes are checked against the script, reads are served from it,
+//! and errors can be injected at any point. The mock panics as soon as the code under test
+//! deviates from the script.
+//!
+//! ```
+//! use embedded_io::blocking::{Read, Write};
+//! use embedded_io::mock::{Mock, Transaction};
+//! use embedded_io::ErrorKind;
+//!
+//! let mut mock = Mock::new([
+//!     Transaction::write(b"AT\r\n"),
+//!     Transaction::Flush,
+//!     Transaction::read(b"OK\r\n"),
+//!     Transaction::Error(ErrorKind::Other),
+//! ]);
+//!
+//! mock.write_all(b"AT\r\n").unwrap();
+//! mock.flush().unwrap();
+//! let mut buf = [0; 4];
+//! mock.read_exact(&mut buf).unwrap();
+//! assert_eq!(&buf, b"OK\r\n");
+//! assert_eq!(mock.read(&mut buf), Err(ErrorKind::Other));
+//! mock.done();
+//! ```
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+
+use crate::{ErrorKind, SeekFrom};
+
+/// A single expected operation in a [`Mock`] script.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Transaction {
+    /// Serve these bytes to reads.
+    ///
+    /// They may be consumed by one or several `read` calls. An empty `Read` makes the
+    /// next `read` return `Ok(0)`, signaling EOF.
+    Read(Vec<u8>),
+    /// Expect these bytes to be written.
+    ///
+    /// They may be written by one or several `write` calls.
+    Write(Vec<u8>),
+    /// Expect a call to `flush`.
+    Flush,
+    /// Expect a seek to the given position, and return the given offset.
+    Seek(SeekFrom, u64),
+    /// Fail the next operation, whatever it is, with this error.
+    Error(ErrorKind),
+}
+
+impl Transaction {
+    /// Serve `data` to reads.
+    pub fn read(data: &[u8]) -> Self {
+        Self::Read(data.to_vec())
+    }
+
+    /// Expect `data` to be written.
+    pub fn write(data: &[u8]) -> Self {
+        Self::Write(data.to_vec())
+    }
+}
+
+/// Scripted mock implementing the blocking and async IO traits.
+///
+/// See the [module-level documentation](self) for details.
+#[derive(Debug, Clone)]
+pub struct Mock {
+    script: VecDeque<Transaction>,
+    /// Number of bytes of the front `Read`/`Write` transaction already transferred.
+    offset: usize,
+    /// Index of the front transaction in the original script, for error messages.
+    index: usize,
+}
+
+impl Mock {
+    /// Create a new mock from a transaction script.
+    pub fn new(script: impl IntoIterator<Item = Transaction>) -> Self {
+        Self {
+            script: script.into_iter().collect(),
+            offset: 0,
+            index: 0,
+        }
+    }
+
+    /// Append transactions to the end of the script.
+    pub fn extend(&mut self, script: impl IntoIterator<Item = Transaction>) {
+        self.script.extend(script)
+    }
+
+    /// Assert the whole script has been consumed.
+    pub fn done(&self) {
+        if let Some(t) = self.script.front() {
+            ::core::panic!(
+                "mock: script not done, {} transaction(s) left, next is #{}: {}",
+                self.script.len(),
+                self.index,
+                describe(t, self.offset),
+            );
+        }
+    }
+
+    fn pop(&mut self) {
+        self.script.pop_front();
+        self.offset = 0;
+        self.index += 1;
+    }
+
+    /// Pop the front transaction if it's an injected error.
+    fn take_error(&mut self) -> Result<(), ErrorKind> {
+        match self.script.front() {
+            Some(&Transaction::Error(kind)) => {
+                self.pop();
+                Err(kind)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn unexpected(&self, op: &str) -> ! {
+        match self.script.front() {
+            Some(t) => ::core::panic!(
+                "mock: unexpected {}, transaction #{} is {}",
+                op,
+                self.index,
+                describe(t, self.offset),
+            ),
+            None => ::core::panic!("mock: unexpected {}, script is done", op),
+        }
+    }
+
+    fn do_read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        let data = self.fill_buf_inner()?;
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        self.consume_inner(n);
+        Ok(n)
+    }
+
+    fn fill_buf_inner(&mut self) -> Result<&[u8], ErrorKind> {
+        self.take_error()?;
+        match self.script.front() {
+            Some(Transaction::Read(data)) => Ok(&data[self.offset..]),
+            _ => self.unexpected("read"),
+        }
+    }
+
+    fn consume_inner(&mut self, amt: usize) {
+        if let Some(Transaction::Read(data)) = self.script.front() {
+            let remaining = data.len() - self.offset;
+            assert!(amt <= remaining);
+            if amt == remaining {
+                self.pop();
+            } else {
+                self.offset += amt;
+            }
+        } else {
+            assert!(amt == 0);
+        }
+    }
+
+    fn do_write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        self.take_error()?;
+        let expected = match self.script.front() {
+            Some(Transaction::Write(data)) => &data[self.offset..],
+            _ => self.unexpected("write"),
+        };
+        let n = expected.len().min(buf.len());
+        if buf[..n] != expected[..n] {
+            let pos = buf.iter().zip(expected).take_while(|(a, b)| a == b).count();
+            ::core::panic!(
+                "mock: write mismatch in transaction #{} at byte {}\n  expected: {}\n  actual:   {}",
+                self.index,
+                self.offset + pos,
+                escape(expected),
+                escape(buf),
+            );
+        }
+        if n == expected.len() {
+            self.pop();
+        } else {
+            self.offset += n;
+        }
+        Ok(n)
+    }
+
+    fn do_flush(&mut self) -> Result<(), ErrorKind> {
+        self.take_error()?;
+        match self.script.front() {
+            Some(Transaction::Flush) => {
+                self.pop();
+                Ok(())
+            }
+            _ => self.unexpected("flush"),
+        }
+    }
+
+    fn do_seek(&mut self, pos: SeekFrom) -> Result<u64, ErrorKind> {
+        self.take_error()?;
+        match self.script.front() {
+            Some(&Transaction::Seek(expected, offset)) => {
+                if pos != expected {
+                    ::core::panic!(
+                        "mock: seek mismatch in transaction #{}\n  expected: {:?}\n  actual:   {:?}",
+                        self.index,
+                        expected,
+                        pos,
+                    );
+                }
+                self.pop();
+                Ok(offset)
+            }
+            _ => self.unexpected("seek"),
+        }
+    }
+}
+
+/// Render bytes as an escaped byte string literal, so protocol text stays readable.
+fn escape(data: &[u8]) -> String {
+    let mut s = String::from("b\"");
+    for &b in data {
+        for c in core::ascii::escape_default(b) {
+            s.push(c as char);
+        }
+    }
+    s.push('"');
+    s
+}
+
+fn describe(t: &Transaction, offset: usize) -> String {
+    let mut s = String::new();
+    let _ = match t {
+        Transaction::Read(data) => write!(s, "read {}", escape(&data[offset..])),
+        Transaction::Write(data) => write!(s, "write {}", escape(&data[offset..])),
+        Transaction::Flush => write!(s, "flush"),
+        Transaction::Seek(pos, _) => write!(s, "seek {:?}", pos),
+        Transaction::Error(kind) => write!(s, "error {:?}", kind),
+    };
+    s
+}
+
+impl crate::Io for Mock {
+    type Error = ErrorKind;
+}
+
+impl crate::blocking::Read for Mock {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.do_read(buf)
+    }
+}
+
+impl crate::blocking::BufRead for Mock {
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        self.fill_buf_inner()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consume_inner(amt)
+    }
+}
+
+impl crate::blocking::Write for Mock {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.do_write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.do_flush()
+    }
+}
+
+impl crate::blocking::Seek for Mock {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        self.do_seek(pos)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl crate::asynch::Read for Mock {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.do_read(buf)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl crate::asynch::BufRead for Mock {
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        self.fill_buf_inner()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.consume_inner(amt)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl crate::asynch::Write for Mock {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.do_write(buf)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.do_flush()
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl crate::asynch::Seek for Mock {
+    async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        self.do_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::{BufRead, Read, Seek, Write};
+
+    #[test]
+    fn short_reads_and_writes() {
+        let mut mock = Mock::new([Transaction::read(b"hello"), Transaction::write(b"world")]);
+
+        let mut buf = [0; 2];
+        assert_eq!(mock.read(&mut buf), Ok(2));
+        assert_eq!(&buf, b"he");
+        assert_eq!(mock.fill_buf(), Ok(&b"llo"[..]));
+        mock.consume(1);
+        let mut buf = [0; 8];
+        assert_eq!(mock.read(&mut buf), Ok(2));
+        assert_eq!(&buf[..2], b"lo");
+
+        assert_eq!(mock.write(b"wor"), Ok(3));
+        // A write past the end of the transaction is cut short.
+        assert_eq!(mock.write(b"ld!"), Ok(2));
+        mock.done();
+    }
+
+    #[test]
+    fn empty_read_is_eof() {
+        let mut mock = Mock::new([Transaction::read(b"")]);
+        assert_eq!(mock.read(&mut [0; 4]), Ok(0));
+        mock.done();
+    }
+
+    #[test]
+    fn injected_errors() {
+        let mut mock = Mock::new([
+            Transaction::Error(ErrorKind::TimedOut),
+            Transaction::write(b"x"),
+            Transaction::Error(ErrorKind::InvalidData),
+            Transaction::Flush,
+        ]);
+        assert_eq!(mock.write(b"x"), Err(ErrorKind::TimedOut));
+        assert_eq!(mock.write(b"x"), Ok(1));
+        assert_eq!(mock.flush(), Err(ErrorKind::InvalidData));
+        assert_eq!(mock.flush(), Ok(()));
+        mock.done();
+    }
+
+    #[test]
+    fn seek() {
+        let mut mock = Mock::new([Transaction::Seek(SeekFrom::End(-2), 8)]);
+        assert_eq!(mock.seek(SeekFrom::End(-2)), Ok(8));
+        mock.done();
+    }
+
+    #[test]
+    fn extend() {
+        let mut mock = Mock::new([]);
+        mock.extend([Transaction::Flush]);
+        assert_eq!(mock.flush(), Ok(()));
+        mock.done();
+    }
+
+    #[test]
+    #[should_panic(expected = "write mismatch in transaction #0 at byte 1")]
+    fn write_mismatch() {
+        let mut mock = Mock::new([Transaction::write(b"AT")]);
+        let _ = mock.write(b"AX");
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected read, transaction #0 is write b\"AT\"")]
+    fn unexpected_operation() {
+        let mut mock = Mock::new([Transaction::write(b"AT")]);
+        let _ = mock.read(&mut [0; 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected flush, script is done")]
+    fn script_exhausted() {
+        let mut mock = Mock::new([]);
+        let _ = mock.flush();
+    }
+
+    #[test]
+    #[should_panic(expected = "seek mismatch")]
+    fn seek_mismatch() {
+        let mut mock = Mock::new([Transaction::Seek(SeekFrom::Start(0), 0)]);
+        let _ = mock.seek(SeekFrom::Current(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "script not done, 1 transaction(s) left, next is #0: read b\"\\n\"")]
+    fn not_done() {
+        let mut mock = Mock::new([Transaction::read(b"a\n")]);
+        let _ = mock.read(&mut [0; 1]);
+        mock.done();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+
+        let mut mock = Mock::new([Transaction::write(b"ping"), Transaction::read(b"pong")]);
+        block_on(
+            async {
+                crate::asynch::Write::write_all(&mut mock, b"ping")
+                    .await
+                    .unwrap();
+                let mut buf = [0; 4];
+                crate::asynch::Read::read_exact(&mut mock, &mut buf)
+                    .await
+                    .unwrap();
+                assert_eq!(&buf, b"pong");
+            },
+            &mut Spin,
+        );
+        mock.done();
+    }
+}
@@ -0,0 +1,343 @@
+//! Integer formatting without `core::fmt`.
+//!
+//! Formatting with `write!` pulls in `core::fmt`, which is large for small microcontrollers.
+//! The [`WriteNumExt`](crate::blocking::WriteNumExt) extension trait, and its
+//! [async](crate::asynch) counterpart, write integers in decimal or hex directly instead.
+//!
+//! 8, 16 and 32-bit integers are formatted with 32-bit arithmetic, so that 64-bit division isn't
+//! needed unless 64-bit integers are written.
+
+/// Maximum formatted length, for `i128::MIN` in decimal.
+pub(crate) const MAX_LEN: usize = 40;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// How to pad a number to a minimum width.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pad {
+    /// Pad with spaces before the number, like `{:5}`.
+    Space,
+    /// Pad with zeros between the sign and the digits, like `{:05}`.
+    Zero,
+}
+
+impl Pad {
+    fn byte(self) -> u8 {
+        match self {
+            Self::Space => b' ',
+            Self::Zero => b'0',
+        }
+    }
+}
+
+pub(crate) mod private {
+    pub trait Sealed: Copy {
+        /// Format in decimal at the end of `out`, returning where it starts.
+        fn dec(self, out: &mut [u8; super::MAX_LEN]) -> usize;
+
+        /// Format in hex at the end of `out`, returning where it starts. Negative numbers are
+        /// formatted as two's complement.
+        fn hex(self, out: &mut [u8; super::MAX_LEN]) -> usize;
+    }
+}
+
+/// Integer types that can be formatted by [`WriteNumExt`](crate::blocking::WriteNumExt).
+///
+/// This trait is sealed, and can't be implemented outside this crate.
+pub trait Integer: private::Sealed {}
+
+/// Digit formatting for the unsigned types arithmetic is done in.
+trait Digits: Copy {
+    fn dec_digits(self, out: &mut [u8; MAX_LEN]) -> usize;
+    fn hex_digits(self, out: &mut [u8; MAX_LEN]) -> usize;
+}
+
+macro_rules! impl_digits {
+    ($($t:ty)*) => {$(
+        impl Digits for $t {
+            fn dec_digits(mut self, out: &mut [u8; MAX_LEN]) -> usize {
+                let mut i = MAX_LEN;
+                loop {
+                    i -= 1;
+                    out[i] = b'0' + (self % 10) as u8;
+                    self /= 10;
+                    if self == 0 {
+                        return i;
+                    }
+                }
+            }
+
+            fn hex_digits(mut self, out: &mut [u8; MAX_LEN]) -> usize {
+                let mut i = MAX_LEN;
+                loop {
+                    i -= 1;
+                    out[i] = HEX_DIGITS[(self & 0xf) as usize];
+                    self >>= 4;
+                    if self == 0 {
+                        return i;
+                    }
+                }
+            }
+        }
+    )*};
+}
+
+impl_digits!(u32 u64 u128);
+
+macro_rules! impl_integer {
+    ($($u:ty, $i:ty => $uwide:ty, $iwide:ty;)*) => {$(
+        impl private::Sealed for $u {
+            fn dec(self, out: &mut [u8; MAX_LEN]) -> usize {
+                (self as $uwide).dec_digits(out)
+            }
+
+            fn hex(self, out: &mut [u8; MAX_LEN]) -> usize {
+                (self as $uwide).hex_digits(out)
+            }
+        }
+
+        impl Integer for $u {}
+
+        impl private::Sealed for $i {
+            fn dec(self, out: &mut [u8; MAX_LEN]) -> usize {
+                let start = (self as $iwide).unsigned_abs().dec_digits(out);
+                if self < 0 {
+                    out[start - 1] = b'-';
+                    start - 1
+                } else {
+                    start
+                }
+            }
+
+            fn hex(self, out: &mut [u8; MAX_LEN]) -> usize {
+                (self as $u as $uwide).hex_digits(out)
+            }
+        }
+
+        impl Integer for $i {}
+    )*};
+}
+
+impl_integer! {
+    u8, i8 => u32, i32;
+    u16, i16 => u32, i32;
+    u32, i32 => u32, i32;
+    u64, i64 => u64, i64;
+    u128, i128 => u128, i128;
+}
+
+#[cfg(not(target_pointer_width = "64"))]
+impl_integer! {
+    usize, isize => u32, i32;
+}
+
+#[cfg(target_pointer_width = "64")]
+impl_integer! {
+    usize, isize => u64, i64;
+}
+
+/// A number formatted for writing: a sign, padding, then digits.
+pub(crate) struct Formatted {
+    buf: [u8; MAX_LEN],
+    start: usize,
+    sign: usize,
+    fill: u8,
+    pad: usize,
+}
+
+impl Formatted {
+    pub(crate) fn dec<T: Integer>(value: T, width: usize, pad: Pad) -> Self {
+        let mut buf = [0; MAX_LEN];
+        let start = value.dec(&mut buf);
+        let sign = usize::from(pad == Pad::Zero && buf[start] == b'-');
+        Self {
+            buf,
+            start,
+            sign,
+            fill: pad.byte(),
+            pad: width.saturating_sub(MAX_LEN - start),
+        }
+    }
+
+    pub(crate) fn hex<T: Integer>(value: T, width: usize) -> Self {
+        let mut buf = [0; MAX_LEN];
+        let start = value.hex(&mut buf);
+        Self {
+            buf,
+            start,
+            sign: 0,
+            fill: b'0',
+            pad: width.saturating_sub(MAX_LEN - start),
+        }
+    }
+
+    /// The sign, if it goes before the padding.
+    pub(crate) fn sign(&self) -> &[u8] {
+        &self.buf[self.start..][..self.sign]
+    }
+
+    /// The padding, in chunks of `buf`'s length, which must be non-zero.
+    pub(crate) fn padding<'a>(&self, buf: &'a mut [u8]) -> impl Iterator<Item = &'a [u8]> {
+        buf.fill(self.fill);
+        let buf = &*buf;
+        let mut pad = self.pad;
+        core::iter::from_fn(move || {
+            let n = core::cmp::min(pad, buf.len());
+            pad -= n;
+            (n > 0).then_some(&buf[..n])
+        })
+    }
+
+    /// The digits, and the sign if it goes after the padding.
+    pub(crate) fn digits(&self) -> &[u8] {
+        &self.buf[self.start + self.sign..]
+    }
+}
+
+/// Format `bytes` in hex into `out`, which must be twice as long.
+pub(crate) fn hex_bytes(bytes: &[u8], out: &mut [u8]) {
+    for (byte, out) in bytes.iter().zip(out.chunks_exact_mut(2)) {
+        out[0] = HEX_DIGITS[usize::from(byte >> 4)];
+        out[1] = HEX_DIGITS[usize::from(byte & 0xf)];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::{self, Write};
+
+    /// Fixed-capacity buffer, for comparing against `core::fmt` without allocating.
+    struct Text {
+        buf: [u8; 64],
+        len: usize,
+    }
+
+    impl Text {
+        fn new() -> Self {
+            Self {
+                buf: [0; 64],
+                len: 0,
+            }
+        }
+
+        fn push(&mut self, bytes: &[u8]) {
+            self.buf[self.len..][..bytes.len()].copy_from_slice(bytes);
+            self.len += bytes.len();
+        }
+
+        fn as_bytes(&self) -> &[u8] {
+            &self.buf[..self.len]
+        }
+    }
+
+    impl fmt::Write for Text {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.push(s.as_bytes());
+            Ok(())
+        }
+    }
+
+    fn render(num: &Formatted, chunk: usize) -> Text {
+        let mut out = Text::new();
+        out.push(num.sign());
+        for padding in num.padding(&mut [0; 16][..chunk]) {
+            out.push(padding);
+        }
+        out.push(num.digits());
+        out
+    }
+
+    fn check<T: Integer + fmt::Display + fmt::LowerHex>(value: T) {
+        for width in [0, 1, 3, 39, 40, 41, 50] {
+            for chunk in [1, 7, 16] {
+                let mut expected = Text::new();
+                write!(expected, "{:w$}", value, w = width).unwrap();
+                let num = Formatted::dec(value, width, Pad::Space);
+                assert_eq!(render(&num, chunk).as_bytes(), expected.as_bytes());
+
+                let mut expected = Text::new();
+                write!(expected, "{:0w$}", value, w = width).unwrap();
+                let num = Formatted::dec(value, width, Pad::Zero);
+                assert_eq!(render(&num, chunk).as_bytes(), expected.as_bytes());
+
+                let mut expected = Text::new();
+                write!(expected, "{:0w$x}", value, w = width).unwrap();
+                let num = Formatted::hex(value, width);
+                assert_eq!(render(&num, chunk).as_bytes(), expected.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn matches_core_fmt() {
+        for v in [0, 1, 9, 10, 99, 100, u8::MAX] {
+            check(v);
+        }
+        for v in [i8::MIN, -100, -10, -9, -1, 0, 1, i8::MAX] {
+            check(v);
+        }
+        check(u16::MAX);
+        check(i16::MIN);
+        check(u32::MAX);
+        check(i32::MIN);
+        check(u64::MAX);
+        check(i64::MIN);
+        check(usize::MAX);
+        check(isize::MIN);
+        for v in [0, u128::MAX, u128::MAX / 10, 1 << 127] {
+            check(v);
+        }
+        for v in [i128::MIN, i128::MIN + 1, -1, i128::MAX] {
+            check(v);
+        }
+    }
+
+    #[test]
+    fn extremes() {
+        let num = Formatted::dec(i128::MIN, 0, Pad::Space);
+        assert_eq!(num.digits(), b"-170141183460469231731687303715884105728");
+        assert_eq!(num.digits().len(), MAX_LEN);
+
+        // Zero padding goes between the sign and the digits.
+        let num = Formatted::dec(-5i8, 4, Pad::Zero);
+        assert_eq!((num.sign(), num.digits()), (&b"-"[..], &b"5"[..]));
+        assert_eq!(render(&num, 16).as_bytes(), b"-005");
+        let num = Formatted::dec(-5i8, 4, Pad::Space);
+        assert_eq!((num.sign(), num.digits()), (&b""[..], &b"-5"[..]));
+        assert_eq!(render(&num, 16).as_bytes(), b"  -5");
+
+        // Hex has no sign: negative numbers are two's complement of their own width.
+        let num = Formatted::hex(-1i16, 6);
+        assert_eq!(render(&num, 16).as_bytes(), b"00ffff");
+        let num = Formatted::hex(i128::MIN, 0);
+        assert_eq!(num.digits().len(), 32);
+        assert_eq!(num.digits()[0], b'8');
+    }
+
+    #[test]
+    fn padding_chunks() {
+        let num = Formatted::dec(7u8, 40, Pad::Space);
+        let mut buf = [0; 16];
+        let mut padding = num.padding(&mut buf);
+        assert_eq!(padding.next().map(<[u8]>::len), Some(16));
+        assert_eq!(padding.next().map(<[u8]>::len), Some(16));
+        assert_eq!(padding.next(), Some(&[b' '; 7][..]));
+        assert_eq!(padding.next(), None);
+
+        let num = Formatted::dec(12345u32, 3, Pad::Zero);
+        assert_eq!(num.padding(&mut [0; 16]).next(), None);
+    }
+
+    #[test]
+    fn hex_bytes_pairs() {
+        let mut out = [0; 8];
+        hex_bytes(&[0x00, 0x0f, 0xa5, 0xff], &mut out);
+        assert_eq!(&out, b"000fa5ff");
+
+        let mut out = [b'x'; 4];
+        hex_bytes(&[], &mut out);
+        assert_eq!(&out, b"xxxx");
+    }
+}
@@ -0,0 +1,439 @@
+//! Timeouts for async IO.
+//!
+//! The async traits have no notion of time: a [`Read::read`](crate::asynch::Read::read) on a
+//! silent UART waits forever. This module races IO operations against a [`Delay`], either
+//! per operation with the [`WithTimeout`] wrapper, or over a whole transfer with the
+//! `*_with_deadline` helpers.
+
+use core::fmt;
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::task::Poll;
+
+use crate::asynch::{BufRead, Read, ReadExactError, Write, WriteAllError};
+
+/// Async delay provider used to time out IO operations.
+///
+/// With the `embedded-hal-async` Cargo feature, this is implemented for every
+/// `embedded_hal_async::delay::DelayUs`.
+pub trait Delay {
+    /// Pauses execution for at minimum `us` microseconds.
+    async fn delay_us(&mut self, us: u32);
+}
+
+#[cfg(feature = "embedded-hal-async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "embedded-hal-async")))]
+impl<T: embedded_hal_async::delay::DelayUs + ?Sized> Delay for T {
+    async fn delay_us(&mut self, us: u32) {
+        embedded_hal_async::delay::DelayUs::delay_us(self, us).await
+    }
+}
+
+/// Error returned by operations with a timeout.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TimeoutError<E> {
+    /// The operation did not complete in time.
+    TimedOut,
+    /// Error returned by the inner IO object.
+    Other(E),
+}
+
+impl<E> From<E> for TimeoutError<E> {
+    fn from(err: E) -> Self {
+        Self::Other(err)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for TimeoutError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for TimeoutError<E> {}
+
+impl<E: crate::Error> crate::Error for TimeoutError<E> {
+    fn kind(&self) -> crate::ErrorKind {
+        match self {
+            Self::TimedOut => crate::ErrorKind::TimedOut,
+            Self::Other(e) => e.kind(),
+        }
+    }
+}
+
+/// Error returned by [`read_exact_with_deadline`] and [`write_all_with_deadline`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DeadlineError<E> {
+    /// The deadline expired. Contains the number of bytes transferred before it did.
+    TimedOut(usize),
+    /// Error returned by the inner operation.
+    Other(E),
+}
+
+impl<E> From<E> for DeadlineError<E> {
+    fn from(err: E) -> Self {
+        Self::Other(err)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for DeadlineError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for DeadlineError<E> {}
+
+impl<E: crate::Error> crate::Error for DeadlineError<E> {
+    fn kind(&self) -> crate::ErrorKind {
+        match self {
+            Self::TimedOut(_) => crate::ErrorKind::TimedOut,
+            Self::Other(e) => e.kind(),
+        }
+    }
+}
+
+/// Run `fut` to completion, unless `delay` elapses first.
+async fn timeout<D: Delay + ?Sized, F: Future>(
+    delay: &mut D,
+    timeout_us: u32,
+    fut: F,
+) -> Option<F::Output> {
+    let mut fut = pin!(fut);
+    let mut timer = pin!(delay.delay_us(timeout_us));
+    poll_fn(|cx| {
+        if let Poll::Ready(r) = fut.as_mut().poll(cx) {
+            return Poll::Ready(Some(r));
+        }
+        if timer.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// Read into `buf`, giving up after `timeout_us` microseconds.
+pub async fn read_with_timeout<R: Read + ?Sized, D: Delay + ?Sized>(
+    reader: &mut R,
+    delay: &mut D,
+    timeout_us: u32,
+    buf: &mut [u8],
+) -> Result<usize, TimeoutError<R::Error>> {
+    match timeout(delay, timeout_us, reader.read(buf)).await {
+        Some(r) => Ok(r?),
+        None => Err(TimeoutError::TimedOut),
+    }
+}
+
+/// Write from `buf`, giving up after `timeout_us` microseconds.
+pub async fn write_with_timeout<W: Write + ?Sized, D: Delay + ?Sized>(
+    writer: &mut W,
+    delay: &mut D,
+    timeout_us: u32,
+    buf: &[u8],
+) -> Result<usize, TimeoutError<W::Error>> {
+    match timeout(delay, timeout_us, writer.write(buf)).await {
+        Some(r) => Ok(r?),
+        None => Err(TimeoutError::TimedOut),
+    }
+}
+
+/// Fill `buf` completely, giving up once `timeout_us` microseconds have elapsed in total.
+///
+/// On timeout, the first `n` bytes of `buf` hold valid data, where `n` is the count
+/// carried by [`DeadlineError::TimedOut`].
+pub async fn read_exact_with_deadline<R: Read + ?Sized, D: Delay + ?Sized>(
+    reader: &mut R,
+    delay: &mut D,
+    timeout_us: u32,
+    buf: &mut [u8],
+) -> Result<(), DeadlineError<ReadExactError<R::Error>>> {
+    let mut read = 0;
+    let fut = async {
+        while read < buf.len() {
+            match reader.read(&mut buf[read..]).await {
+                Ok(0) => return Err(ReadExactError::UnexpectedEof),
+                Ok(n) => read += n,
+                Err(e) => return Err(ReadExactError::Other(e)),
+            }
+        }
+        Ok(())
+    };
+    match timeout(delay, timeout_us, fut).await {
+        Some(r) => Ok(r?),
+        None => Err(DeadlineError::TimedOut(read)),
+    }
+}
+
+/// Write all of `buf`, giving up once `timeout_us` microseconds have elapsed in total.
+///
+/// On timeout, the first `n` bytes of `buf` have been written, where `n` is the count
+/// carried by [`DeadlineError::TimedOut`].
+pub async fn write_all_with_deadline<W: Write + ?Sized, D: Delay + ?Sized>(
+    writer: &mut W,
+    delay: &mut D,
+    timeout_us: u32,
+    buf: &[u8],
+) -> Result<(), DeadlineError<WriteAllError<W::Error>>> {
+    let mut written = 0;
+    let fut = async {
+        while written < buf.len() {
+            match writer.write(&buf[written..]).await {
+                Ok(0) => return Err(WriteAllError::WriteZero),
+                Ok(n) => written += n,
+                Err(e) => return Err(WriteAllError::Other(e)),
+            }
+        }
+        Ok(())
+    };
+    match timeout(delay, timeout_us, fut).await {
+        Some(r) => Ok(r?),
+        None => Err(DeadlineError::TimedOut(written)),
+    }
+}
+
+/// Wrapper applying a timeout to every operation on the inner IO object.
+///
+/// Each call to `read`, `fill_buf`, `write` or `flush` is raced against the delay and fails
+/// with [`TimeoutError::TimedOut`] if it does not complete in time. Provided methods such as
+/// `read_exact` get a fresh timeout for every underlying call; use
+/// [`read_exact_with_deadline`] or [`write_all_with_deadline`] to bound a whole transfer.
+pub struct WithTimeout<T, D> {
+    inner: T,
+    delay: D,
+    timeout_us: u32,
+}
+
+impl<T, D> WithTimeout<T, D> {
+    /// Create a new wrapper, timing out operations after `timeout_us` microseconds.
+    pub fn new(inner: T, delay: D, timeout_us: u32) -> Self {
+        Self {
+            inner,
+            delay,
+            timeout_us,
+        }
+    }
+
+    /// Consume the wrapper, returning the inner object and the delay.
+    pub fn into_inner(self) -> (T, D) {
+        (self.inner, self.delay)
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Get the timeout, in microseconds.
+    pub fn timeout_us(&self) -> u32 {
+        self.timeout_us
+    }
+
+    /// Set the timeout, in microseconds.
+    pub fn set_timeout_us(&mut self, timeout_us: u32) {
+        self.timeout_us = timeout_us;
+    }
+}
+
+impl<T: crate::Io, D> crate::Io for WithTimeout<T, D> {
+    type Error = TimeoutError<T::Error>;
+}
+
+impl<T: Read, D: Delay> Read for WithTimeout<T, D> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        read_with_timeout(&mut self.inner, &mut self.delay, self.timeout_us, buf).await
+    }
+}
+
+impl<T: BufRead, D: Delay> BufRead for WithTimeout<T, D> {
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        match timeout(&mut self.delay, self.timeout_us, self.inner.fill_buf()).await {
+            Some(r) => Ok(r?),
+            None => Err(TimeoutError::TimedOut),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+impl<T: Write, D: Delay> Write for WithTimeout<T, D> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        write_with_timeout(&mut self.inner, &mut self.delay, self.timeout_us, buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        match timeout(&mut self.delay, self.timeout_us, self.inner.flush()).await {
+            Some(r) => Ok(r?),
+            None => Err(TimeoutError::TimedOut),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::block_on;
+    use crate::chaos::{Chaotic, ChaoticError, Fault};
+    use crate::ErrorKind;
+
+    /// Delay that takes one poll per microsecond, counting the delays that were waited on.
+    ///
+    /// A delay only starts when first polled, after the operation it races was pending.
+    #[derive(Default)]
+    struct Ticks {
+        started: u32,
+    }
+
+    impl Delay for Ticks {
+        async fn delay_us(&mut self, us: u32) {
+            self.started += 1;
+            crate::asynch::yield_n(us).await;
+        }
+    }
+
+    fn pending(n: u32) -> core::iter::Repeat<Fault> {
+        core::iter::repeat(Fault::NONE.with_pending(n))
+    }
+
+    #[test]
+    fn single_operations() {
+        let mut delay = Ticks::default();
+        let mut polls = 0;
+        let mut buf = [0; 4];
+
+        // An operation pending for as long as the timeout still completes.
+        let mut r = Chaotic::new(&b"abc"[..], pending(3));
+        let read = read_with_timeout(&mut r, &mut delay, 3, &mut buf);
+        assert_eq!(block_on(read, &mut || polls += 1), Ok(3));
+        assert_eq!(polls, 3);
+
+        let mut r = Chaotic::new(&b"abc"[..], pending(3));
+        let read = read_with_timeout(&mut r, &mut delay, 2, &mut buf);
+        assert_eq!(
+            block_on(read, &mut || polls += 1),
+            Err(TimeoutError::TimedOut)
+        );
+        assert_eq!(polls, 5);
+        // The timed out read was dropped before taking any data.
+        assert_eq!(r.into_inner(), b"abc");
+
+        let mut w = Chaotic::new(&mut buf[..], pending(1));
+        let write = write_with_timeout(&mut w, &mut delay, 1, b"xy");
+        assert_eq!(block_on(write, &mut || polls += 1), Ok(2));
+        let write = write_with_timeout(&mut w, &mut delay, 0, b"z");
+        assert_eq!(
+            block_on(write, &mut || polls += 1),
+            Err(TimeoutError::TimedOut)
+        );
+        assert_eq!(delay.started, 4);
+
+        let faults = [Fault::error(ErrorKind::Other)];
+        let mut r = Chaotic::new(&b"abc"[..], faults.into_iter());
+        let read = read_with_timeout(&mut r, &mut delay, 0, &mut buf);
+        let err = TimeoutError::Other(ChaoticError::Injected(ErrorKind::Other));
+        assert_eq!(block_on(read, &mut || polls += 1), Err(err));
+        assert_eq!(crate::Error::kind(&err), ErrorKind::Other);
+        let timed_out = TimeoutError::<ErrorKind>::TimedOut;
+        assert_eq!(crate::Error::kind(&timed_out), ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn deadlines() {
+        let mut delay = Ticks::default();
+        let mut buf = [0; 8];
+
+        // Each read of two bytes completes on the poll after it starts, so by the time a
+        // deadline of three polls expires, six bytes are read.
+        let faults = core::iter::repeat(Fault::limit(2).with_pending(1));
+        let mut r = Chaotic::new(&b"abcdefgh"[..], faults.clone());
+        let read = read_exact_with_deadline(&mut r, &mut delay, 3, &mut buf);
+        assert_eq!(block_on(read, &mut || {}), Err(DeadlineError::TimedOut(6)));
+        assert_eq!(&buf[..6], b"abcdef");
+        assert_eq!(delay.started, 1);
+
+        let mut r = Chaotic::new(&b"abcdefgh"[..], faults.clone());
+        let read = read_exact_with_deadline(&mut r, &mut delay, 4, &mut buf);
+        assert_eq!(block_on(read, &mut || {}), Ok(()));
+        assert_eq!(&buf, b"abcdefgh");
+
+        let mut r = Chaotic::new(&b"abc"[..], faults.clone());
+        let read = read_exact_with_deadline(&mut r, &mut delay, 100, &mut buf);
+        assert_eq!(
+            block_on(read, &mut || {}),
+            Err(DeadlineError::Other(ReadExactError::UnexpectedEof))
+        );
+
+        let mut out = [0; 8];
+        let mut w = Chaotic::new(&mut out[..], faults.clone());
+        let write = write_all_with_deadline(&mut w, &mut delay, 2, b"12345678");
+        assert_eq!(block_on(write, &mut || {}), Err(DeadlineError::TimedOut(4)));
+        let mut w = Chaotic::new(&mut out[4..], faults);
+        let write = write_all_with_deadline(&mut w, &mut delay, 100, b"567890");
+        assert_eq!(
+            block_on(write, &mut || {}),
+            Err(DeadlineError::Other(WriteAllError::WriteZero))
+        );
+        assert_eq!(&out, b"12345678");
+        assert_eq!(
+            crate::Error::kind(&DeadlineError::<ErrorKind>::TimedOut(4)),
+            ErrorKind::TimedOut
+        );
+    }
+
+    #[test]
+    fn with_timeout() {
+        let faults = [
+            Fault::NONE.with_pending(2),
+            Fault::NONE.with_pending(1),
+            Fault::NONE.with_pending(1),
+            Fault::limit(1).with_pending(1),
+            Fault::limit(1).with_pending(1),
+        ];
+        let r = Chaotic::new(&b"abcde"[..], faults.into_iter());
+        let mut r = WithTimeout::new(r, Ticks::default(), 1);
+        let mut buf = [0; 2];
+        assert_eq!(
+            block_on(r.read(&mut buf), &mut || {}),
+            Err(TimeoutError::TimedOut)
+        );
+        assert_eq!(block_on(r.read(&mut buf), &mut || {}), Ok(2));
+        assert_eq!(block_on(r.fill_buf(), &mut || {}), Ok(&b"cde"[..]));
+        r.consume(1);
+        // `read_exact` gets a fresh timeout for every read.
+        assert_eq!(block_on(r.read_exact(&mut buf), &mut || {}), Ok(()));
+        assert_eq!(&buf, b"de");
+        let (r, delay) = r.into_inner();
+        assert_eq!(r.into_inner(), b"");
+        assert_eq!(delay.started, 5);
+
+        let faults = [
+            Fault::NONE.with_pending(1),
+            Fault::NONE.with_pending(2),
+            Fault::NONE.with_pending(5),
+        ];
+        let mut out = [0; 4];
+        let w = Chaotic::new(&mut out[..], faults.into_iter());
+        let mut w = WithTimeout::new(w, Ticks::default(), 0);
+        w.set_timeout_us(2);
+        assert_eq!(w.timeout_us(), 2);
+        assert_eq!(block_on(w.write(b"ab"), &mut || {}), Ok(2));
+        assert_eq!(block_on(w.flush(), &mut || {}), Ok(()));
+        assert_eq!(
+            block_on(w.write(b"cd"), &mut || {}),
+            Err(TimeoutError::TimedOut)
+        );
+        assert_eq!(w.inner().inner().len(), 2);
+    }
+}
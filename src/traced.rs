@@ -0,0 +1,451 @@
+//! Logging of IO operations, through the `log` or `defmt` Cargo features.
+
+use core::fmt;
+
+/// Level [`Traced`] logs at.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Level {
+    /// Trace level.
+    Trace,
+    /// Debug level.
+    Debug,
+    /// Info level.
+    Info,
+    /// Warn level.
+    Warn,
+    /// Error level.
+    Error,
+}
+
+macro_rules! log_at {
+    ($level:expr, $($arg:tt)*) => {
+        match $level {
+            Level::Trace => trace!($($arg)*),
+            Level::Debug => debug!($($arg)*),
+            Level::Info => info!($($arg)*),
+            Level::Warn => warn!($($arg)*),
+            Level::Error => error!($($arg)*),
+        }
+    };
+}
+
+/// Hexdump of a byte slice, truncated to a maximum length.
+struct Hex<'a> {
+    data: &'a [u8],
+    truncated: bool,
+}
+
+impl<'a> Hex<'a> {
+    fn new(data: &'a [u8], max: usize) -> Self {
+        Self {
+            data: &data[..data.len().min(max)],
+            truncated: data.len() > max,
+        }
+    }
+}
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, b) in self.data.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        if self.truncated {
+            f.write_str(" ...")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Hex<'_> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "{=[u8]:02x}", self.data);
+        if self.truncated {
+            defmt::write!(f, " ...");
+        }
+    }
+}
+
+/// Wrapper logging every operation on the inner IO object.
+///
+/// Each read, write, flush and seek is logged at the configured [`Level`], along with the
+/// number of bytes transferred, or the [`ErrorKind`](crate::ErrorKind) on failure. Optionally,
+/// a hexdump of the data is included, truncated to a maximum length.
+pub struct Traced<T> {
+    inner: T,
+    config: Config,
+}
+
+#[derive(Copy, Clone)]
+struct Config {
+    name: &'static str,
+    level: Level,
+    hexdump: usize,
+}
+
+impl<T> Traced<T> {
+    /// Create a new wrapper, logging at debug level without hexdumps.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            config: Config {
+                name: "io",
+                level: Level::Debug,
+                hexdump: 0,
+            },
+        }
+    }
+
+    /// Set the name prefixed to log messages, to tell several wrappers apart.
+    pub fn name(mut self, name: &'static str) -> Self {
+        self.config.name = name;
+        self
+    }
+
+    /// Set the level messages are logged at.
+    pub fn level(mut self, level: Level) -> Self {
+        self.config.level = level;
+        self
+    }
+
+    /// Include up to `max` bytes of data in the log messages. Zero disables hexdumps.
+    pub fn hexdump(mut self, max: usize) -> Self {
+        self.config.hexdump = max;
+        self
+    }
+
+    /// Consume the wrapper, returning the inner object.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Borrow the inner object.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Mutably borrow the inner object.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl Config {
+    fn log_transfer<E: crate::Error>(&self, op: &str, res: &Result<usize, E>, data: &[u8]) {
+        match res {
+            Ok(n) if self.hexdump != 0 => log_at!(
+                self.level,
+                "{}: {} {}/{}: {}",
+                self.name,
+                op,
+                n,
+                data.len(),
+                Hex::new(&data[..*n], self.hexdump)
+            ),
+            Ok(n) => log_at!(self.level, "{}: {} {}/{}", self.name, op, n, data.len()),
+            Err(e) => log_at!(self.level, "{}: {} error: {:?}", self.name, op, e.kind()),
+        }
+    }
+
+    fn log_fill_buf<E: crate::Error>(&self, res: &Result<&[u8], E>) {
+        match res {
+            Ok(data) if self.hexdump != 0 => log_at!(
+                self.level,
+                "{}: fill_buf {}: {}",
+                self.name,
+                data.len(),
+                Hex::new(data, self.hexdump)
+            ),
+            Ok(data) => log_at!(self.level, "{}: fill_buf {}", self.name, data.len()),
+            Err(e) => log_at!(self.level, "{}: fill_buf error: {:?}", self.name, e.kind()),
+        }
+    }
+
+    fn log_consume(&self, amt: usize) {
+        log_at!(self.level, "{}: consume {}", self.name, amt)
+    }
+
+    fn log_flush<E: crate::Error>(&self, res: &Result<(), E>) {
+        match res {
+            Ok(()) => log_at!(self.level, "{}: flush", self.name),
+            Err(e) => log_at!(self.level, "{}: flush error: {:?}", self.name, e.kind()),
+        }
+    }
+
+    fn log_seek<E: crate::Error>(&self, pos: crate::SeekFrom, res: &Result<u64, E>) {
+        match res {
+            Ok(offset) => log_at!(self.level, "{}: seek {:?}: {}", self.name, pos, offset),
+            Err(e) => log_at!(
+                self.level,
+                "{}: seek {:?} error: {:?}",
+                self.name,
+                pos,
+                e.kind()
+            ),
+        }
+    }
+}
+
+impl<T: crate::Io> crate::Io for Traced<T> {
+    type Error = T::Error;
+}
+
+impl<T: crate::blocking::Read> crate::blocking::Read for Traced<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let res = self.inner.read(buf);
+        self.config.log_transfer("read", &res, buf);
+        res
+    }
+}
+
+impl<T: crate::blocking::BufRead> crate::blocking::BufRead for Traced<T> {
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        let res = self.inner.fill_buf();
+        self.config.log_fill_buf(&res);
+        res
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.config.log_consume(amt);
+        self.inner.consume(amt)
+    }
+}
+
+impl<T: crate::blocking::Write> crate::blocking::Write for Traced<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let res = self.inner.write(buf);
+        self.config.log_transfer("write", &res, buf);
+        res
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        let res = self.inner.flush();
+        self.config.log_flush(&res);
+        res
+    }
+}
+
+impl<T: crate::blocking::Seek> crate::blocking::Seek for Traced<T> {
+    fn seek(&mut self, pos: crate::SeekFrom) -> Result<u64, Self::Error> {
+        let res = self.inner.seek(pos);
+        self.config.log_seek(pos, &res);
+        res
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::Read> crate::asynch::Read for Traced<T> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let res = self.inner.read(buf).await;
+        self.config.log_transfer("read", &res, buf);
+        res
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::BufRead> crate::asynch::BufRead for Traced<T> {
+    async fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        let res = self.inner.fill_buf().await;
+        self.config.log_fill_buf(&res);
+        res
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.config.log_consume(amt);
+        self.inner.consume(amt)
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::Write> crate::asynch::Write for Traced<T> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let res = self.inner.write(buf).await;
+        self.config.log_transfer("write", &res, buf);
+        res
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        let res = self.inner.flush().await;
+        self.config.log_flush(&res);
+        res
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+impl<T: crate::asynch::Seek> crate::asynch::Seek for Traced<T> {
+    async fn seek(&mut self, pos: crate::SeekFrom) -> Result<u64, Self::Error> {
+        let res = self.inner.seek(pos).await;
+        self.config.log_seek(pos, &res);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hexdump() {
+        extern crate std;
+        use std::string::ToString;
+
+        assert_eq!(Hex::new(&[], 4).to_string(), "");
+        assert_eq!(Hex::new(&[0x01, 0xab], 4).to_string(), "01 ab");
+        assert_eq!(Hex::new(&[1, 2, 3, 4, 5], 4).to_string(), "01 02 03 04 ...");
+    }
+
+    #[cfg(all(feature = "log", feature = "std"))]
+    mod log {
+        use std::cell::RefCell;
+        use std::string::{String, ToString};
+        use std::vec::Vec;
+
+        use super::super::*;
+        use crate::blocking::{BufRead, Read, Seek, Write};
+        use crate::mock::{Mock, Transaction};
+        use crate::{ErrorKind, SeekFrom};
+
+        std::thread_local! {
+            static RECORDS: RefCell<Vec<(::log::Level, String)>> = RefCell::new(Vec::new());
+        }
+
+        /// Logger recording messages of the current thread, since tests run in parallel.
+        struct Recorder;
+
+        impl ::log::Log for Recorder {
+            fn enabled(&self, _: &::log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &::log::Record) {
+                let msg = (record.level(), record.args().to_string());
+                RECORDS.with(|r| r.borrow_mut().push(msg));
+            }
+
+            fn flush(&self) {}
+        }
+
+        fn take_records() -> Vec<(::log::Level, String)> {
+            static LOGGER: Recorder = Recorder;
+            let _ = ::log::set_logger(&LOGGER);
+            ::log::set_max_level(::log::LevelFilter::Trace);
+            RECORDS.with(|r| r.take())
+        }
+
+        fn messages() -> Vec<String> {
+            take_records().into_iter().map(|(_, msg)| msg).collect()
+        }
+
+        #[test]
+        fn transfers() {
+            take_records();
+            let mock = Mock::new([
+                Transaction::read(b"abcdef"),
+                Transaction::write(b"xy"),
+                Transaction::Flush,
+                Transaction::Seek(SeekFrom::Start(3), 3),
+            ]);
+            let mut traced = Traced::new(mock).name("uart").hexdump(4);
+
+            let mut buf = [0; 2];
+            assert_eq!(traced.read(&mut buf), Ok(2));
+            assert_eq!(traced.fill_buf(), Ok(&b"cdef"[..]));
+            traced.consume(4);
+            // A short write is passed through unchanged.
+            assert_eq!(traced.write(b"xyz"), Ok(2));
+            assert_eq!(traced.flush(), Ok(()));
+            assert_eq!(traced.seek(SeekFrom::Start(3)), Ok(3));
+            traced.into_inner().done();
+
+            assert_eq!(
+                messages(),
+                [
+                    "uart: read 2/2: 61 62",
+                    "uart: fill_buf 4: 63 64 65 66",
+                    "uart: consume 4",
+                    "uart: write 2/3: 78 79",
+                    "uart: flush",
+                    "uart: seek Start(3): 3",
+                ]
+            );
+        }
+
+        #[test]
+        fn errors() {
+            take_records();
+            let mock = Mock::new([
+                Transaction::Error(ErrorKind::TimedOut),
+                Transaction::Error(ErrorKind::Other),
+                Transaction::Error(ErrorKind::InvalidInput),
+            ]);
+            let mut traced = Traced::new(mock);
+            assert_eq!(traced.write(b"a"), Err(ErrorKind::TimedOut));
+            assert_eq!(traced.flush(), Err(ErrorKind::Other));
+            assert_eq!(
+                traced.seek(SeekFrom::Current(-1)),
+                Err(ErrorKind::InvalidInput)
+            );
+            assert_eq!(
+                messages(),
+                [
+                    "io: write error: TimedOut",
+                    "io: flush error: Other",
+                    "io: seek Current(-1) error: InvalidInput",
+                ]
+            );
+        }
+
+        #[test]
+        fn levels_and_truncation() {
+            take_records();
+            let mock = Mock::new([Transaction::read(b"abcdef"), Transaction::read(b"")]);
+            let mut traced = Traced::new(mock).level(Level::Warn).hexdump(3);
+            let mut buf = [0; 8];
+            assert_eq!(traced.read(&mut buf), Ok(6));
+            assert_eq!(traced.read(&mut buf), Ok(0));
+            assert_eq!(
+                take_records(),
+                [
+                    (::log::Level::Warn, "io: read 6/8: 61 62 63 ...".to_string()),
+                    (::log::Level::Warn, "io: read 0/8: ".to_string()),
+                ]
+            );
+        }
+
+        #[cfg(feature = "async")]
+        #[test]
+        fn asynch() {
+            use crate::adapters::{block_on, Spin};
+
+            take_records();
+            let mock = Mock::new([Transaction::write(b"hi"), Transaction::read(b"ok")]);
+            let mut traced = Traced::new(mock).level(Level::Trace);
+            let mut buf = [0; 4];
+            block_on(
+                async {
+                    crate::asynch::Write::write_all(&mut traced, b"hi")
+                        .await
+                        .unwrap();
+                    crate::asynch::Read::read(&mut traced, &mut buf).await
+                },
+                &mut Spin,
+            )
+            .unwrap();
+            assert_eq!(
+                take_records(),
+                [
+                    (::log::Level::Trace, "io: write 2/2".to_string()),
+                    (::log::Level::Trace, "io: read 2/4".to_string()),
+                ]
+            );
+        }
+    }
+}
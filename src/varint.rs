@@ -0,0 +1,358 @@
+//! LEB128 varint and zigzag encodings.
+//!
+//! Unsigned integers are encoded as LEB128: 7 bits per byte, least significant group first,
+//! with the high bit set on all bytes but the last. Signed integers are first zigzag-mapped,
+//! so that numbers with a small absolute value have a short encoding.
+//!
+//! Reading and writing is done with the [`ReadVarint`](crate::blocking::ReadVarint) and
+//! [`WriteVarint`](crate::blocking::WriteVarint) extension traits, or their
+//! [async](crate::asynch) counterparts.
+
+use core::fmt;
+
+use crate::blocking::ReadExactError;
+
+/// Maximum encoded length of a varint, for `u128`.
+pub(crate) const MAX_LEN: usize = 19;
+
+/// Error returned by [`ReadVarint`](crate::blocking::ReadVarint).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadVarintError<E> {
+    /// The encoding is malformed, or the value does not fit in the requested type.
+    InvalidData,
+    /// An EOF error was encountered before reading the whole varint.
+    UnexpectedEof,
+    /// Error returned by the inner Read.
+    Other(E),
+}
+
+impl<E> From<E> for ReadVarintError<E> {
+    fn from(err: E) -> Self {
+        Self::Other(err)
+    }
+}
+
+impl<E> From<ReadExactError<E>> for ReadVarintError<E> {
+    fn from(err: ReadExactError<E>) -> Self {
+        match err {
+            ReadExactError::UnexpectedEof => Self::UnexpectedEof,
+            ReadExactError::Other(e) => Self::Other(e),
+        }
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for ReadVarintError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for ReadVarintError<E> {}
+
+impl<E: crate::Error> crate::Error for ReadVarintError<E> {
+    fn kind(&self) -> crate::ErrorKind {
+        match self {
+            Self::InvalidData => crate::ErrorKind::InvalidData,
+            Self::UnexpectedEof => crate::ErrorKind::UnexpectedEof,
+            Self::Other(e) => e.kind(),
+        }
+    }
+}
+
+pub(crate) mod private {
+    pub trait Sealed: Sized + Copy {
+        const ZERO: Self;
+
+        /// Encode into `out`, returning the encoded length.
+        fn encode(self, out: &mut [u8; super::MAX_LEN]) -> usize;
+
+        /// Add the 7-bit group of `byte` at `shift` to `value`, failing on overflow.
+        fn add_group(value: &mut Self, shift: u32, byte: u8) -> Result<(), ()>;
+    }
+
+    pub trait SealedZigZag {}
+}
+
+/// Unsigned integer types that can be encoded as varints.
+///
+/// This trait is sealed, and can't be implemented outside this crate.
+pub trait Varint: private::Sealed {}
+
+/// Signed integer types that can be encoded as zigzag varints.
+///
+/// This trait is sealed, and can't be implemented outside this crate.
+pub trait ZigZag: private::SealedZigZag + Sized {
+    /// Unsigned type of the same width.
+    type Unsigned: Varint;
+
+    /// Map to an unsigned integer, interleaving positive and negative numbers.
+    fn zigzag(self) -> Self::Unsigned;
+
+    /// Inverse of [`zigzag`](Self::zigzag).
+    fn unzigzag(value: Self::Unsigned) -> Self;
+}
+
+macro_rules! impl_varint {
+    ($($u:ty, $i:ty;)*) => {$(
+        impl private::Sealed for $u {
+            const ZERO: Self = 0;
+
+            fn encode(mut self, out: &mut [u8; MAX_LEN]) -> usize {
+                let mut n = 0;
+                loop {
+                    let byte = (self & 0x7f) as u8;
+                    self >>= 7;
+                    if self == 0 {
+                        out[n] = byte;
+                        return n + 1;
+                    }
+                    out[n] = byte | 0x80;
+                    n += 1;
+                }
+            }
+
+            fn add_group(value: &mut Self, shift: u32, byte: u8) -> Result<(), ()> {
+                let bits = (byte & 0x7f) as $u;
+                if shift >= <$u>::BITS || (bits << shift) >> shift != bits {
+                    return Err(());
+                }
+                *value |= bits << shift;
+                Ok(())
+            }
+        }
+
+        impl Varint for $u {}
+
+        impl private::SealedZigZag for $i {}
+
+        impl ZigZag for $i {
+            type Unsigned = $u;
+
+            fn zigzag(self) -> $u {
+                ((self << 1) ^ (self >> (<$i>::BITS - 1))) as $u
+            }
+
+            fn unzigzag(value: $u) -> $i {
+                ((value >> 1) as $i) ^ -((value & 1) as $i)
+            }
+        }
+    )*};
+}
+
+impl_varint! {
+    u16, i16;
+    u32, i32;
+    u64, i64;
+    u128, i128;
+    usize, isize;
+}
+
+/// Incremental varint decoder.
+pub(crate) struct Decoder<T> {
+    value: T,
+    shift: u32,
+}
+
+impl<T: Varint> Decoder<T> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            value: T::ZERO,
+            shift: 0,
+        }
+    }
+
+    /// Feed a byte, returning the value once complete.
+    ///
+    /// Fails if the encoding is longer than the type allows, or the value overflows it.
+    pub(crate) fn push(&mut self, byte: u8) -> Result<Option<T>, ()> {
+        T::add_group(&mut self.value, self.shift, byte)?;
+        self.shift += 7;
+        if byte & 0x80 == 0 {
+            Ok(Some(self.value))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Encode `value` into `out`, returning the encoded length.
+pub(crate) fn encode<T: Varint>(value: T, out: &mut [u8; MAX_LEN]) -> usize {
+    value.encode(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocking::{ReadVarint, WriteVarint};
+    use crate::Error as _;
+    use crate::ErrorKind;
+
+    fn encoded<T: Varint>(value: T) -> ([u8; MAX_LEN], usize) {
+        let mut out = [0; MAX_LEN];
+        let n = encode(value, &mut out);
+        (out, n)
+    }
+
+    #[test]
+    fn encoding() {
+        let (out, n) = encoded(0u32);
+        assert_eq!(&out[..n], &[0x00]);
+        let (out, n) = encoded(127u32);
+        assert_eq!(&out[..n], &[0x7F]);
+        let (out, n) = encoded(128u32);
+        assert_eq!(&out[..n], &[0x80, 0x01]);
+        let (out, n) = encoded(300u16);
+        assert_eq!(&out[..n], &[0xAC, 0x02]);
+        let (out, n) = encoded(u64::MAX);
+        assert_eq!(
+            &out[..n],
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]
+        );
+        let (out, n) = encoded(u128::MAX);
+        assert_eq!(n, MAX_LEN);
+        assert_eq!(out[MAX_LEN - 1], 0x03);
+    }
+
+    #[test]
+    fn round_trip() {
+        fn check<T: Varint + PartialEq + fmt::Debug>(value: T) {
+            let mut buf = [0; MAX_LEN + 1];
+            let mut w = &mut buf[..];
+            w.write_varint(value).unwrap();
+            let n = MAX_LEN + 1 - w.len();
+            let mut r = &buf[..n + 1];
+            assert_eq!(r.read_varint::<T>(), Ok(value));
+            // Nothing past the varint is consumed.
+            assert_eq!(r.len(), 1);
+        }
+
+        for shift in 0..16 {
+            check(1u16 << shift);
+            check((1u16 << shift) - 1);
+        }
+        for shift in 0..64 {
+            check(1u64 << shift);
+            check((1u64 << shift) - 1);
+        }
+        check(u16::MAX);
+        check(u32::MAX);
+        check(u64::MAX);
+        check(u128::MAX);
+        check(usize::MAX);
+    }
+
+    #[test]
+    fn zigzag() {
+        assert_eq!(0i32.zigzag(), 0);
+        assert_eq!((-1i32).zigzag(), 1);
+        assert_eq!(1i32.zigzag(), 2);
+        assert_eq!((-2i32).zigzag(), 3);
+        assert_eq!(i32::MIN.zigzag(), u32::MAX);
+        assert_eq!(i64::MAX.zigzag(), u64::MAX - 1);
+
+        for value in [0, 1, -1, 63, -64, 64, -65, i64::MIN, i64::MAX] {
+            let mut buf = [0; MAX_LEN];
+            let mut w = &mut buf[..];
+            w.write_zigzag(value).unwrap();
+            assert_eq!((&buf[..]).read_zigzag::<i64>(), Ok(value));
+        }
+        let mut buf = [0; MAX_LEN];
+        (&mut buf[..]).write_zigzag(-64i16).unwrap();
+        assert_eq!(buf[0], 0x7F);
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!((&[0xFF, 0xFF, 0x03][..]).read_varint::<u16>(), Ok(u16::MAX));
+        assert_eq!(
+            (&[0xFF, 0xFF, 0x04][..]).read_varint::<u16>(),
+            Err(ReadVarintError::InvalidData)
+        );
+        assert_eq!(
+            (&[0xFF, 0xFF, 0xFF, 0xFF, 0x1F][..]).read_varint::<u32>(),
+            Err(ReadVarintError::InvalidData)
+        );
+        let mut max = [0xFF; 10];
+        max[9] = 0x01;
+        assert_eq!((&max[..]).read_varint::<u64>(), Ok(u64::MAX));
+        max[9] = 0x02;
+        assert_eq!(
+            (&max[..]).read_varint::<u64>(),
+            Err(ReadVarintError::InvalidData)
+        );
+        // Too long, even though the value is zero.
+        assert_eq!(
+            (&[0x80, 0x80, 0x80, 0x00][..]).read_varint::<u16>(),
+            Err(ReadVarintError::InvalidData)
+        );
+        assert_eq!(
+            (&[0x80; 11][..]).read_varint::<u64>(),
+            Err(ReadVarintError::InvalidData)
+        );
+    }
+
+    #[test]
+    fn eof() {
+        assert_eq!(
+            (&[][..]).read_varint::<u32>(),
+            Err(ReadVarintError::UnexpectedEof)
+        );
+        assert_eq!(
+            (&[0x80, 0x80][..]).read_varint::<u32>(),
+            Err(ReadVarintError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn error_kind() {
+        let err: ReadVarintError<ErrorKind> = ReadVarintError::InvalidData;
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err: ReadVarintError<ErrorKind> = ReadVarintError::UnexpectedEof;
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        let err = ReadVarintError::Other(ErrorKind::TimedOut);
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn asynch() {
+        use crate::adapters::{block_on, Spin};
+
+        let mut buf = [0; 8];
+        let mut w = &mut buf[..];
+        block_on(
+            crate::asynch::WriteVarint::write_zigzag(&mut w, -300i32),
+            &mut Spin,
+        )
+        .unwrap();
+        block_on(
+            crate::asynch::WriteVarint::write_varint(&mut w, 300u32),
+            &mut Spin,
+        )
+        .unwrap();
+        let mut r = &buf[..4];
+        assert_eq!(
+            block_on(
+                crate::asynch::ReadVarint::read_zigzag::<i32>(&mut r),
+                &mut Spin
+            ),
+            Ok(-300)
+        );
+        assert_eq!(
+            block_on(
+                crate::asynch::ReadVarint::read_varint::<u32>(&mut r),
+                &mut Spin
+            ),
+            Ok(300)
+        );
+        assert_eq!(
+            block_on(
+                crate::asynch::ReadVarint::read_varint::<u32>(&mut r),
+                &mut Spin
+            ),
+            Err(ReadVarintError::UnexpectedEof)
+        );
+    }
+}